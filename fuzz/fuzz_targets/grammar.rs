@@ -0,0 +1,28 @@
+#![no_main]
+
+use ezlang::files::DeniedFileSystem;
+use ezlang::interpreter::*;
+use ezlang::time::VirtualClock;
+use ezlang::{run_source, STACK_SIZE};
+use ezlang_fuzz::generate_program;
+use libfuzzer_sys::fuzz_target;
+
+// Raw bytes rarely get past the tokenizer, so build well-formed programs instead
+fuzz_target!(|data: &[u8]| {
+    let source = generate_program(data);
+    // libFuzzer's thread has a small stack, run on one as large as the `ezlang` binary's
+    std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_stack_size(STACK_SIZE);
+            // `time.sleep` must not stall the fuzzer
+            interpreter.set_clock(Box::new(VirtualClock::new()));
+            // Programs may not touch the files of the machine running the fuzzer
+            interpreter.set_file_system(Box::new(DeniedFileSystem));
+            let _ = run_source(&mut interpreter, &source, false);
+        })
+        .expect("failed to spawn the interpreter thread")
+        .join()
+        .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+});
@@ -0,0 +1,29 @@
+#![no_main]
+
+use ezlang::files::DeniedFileSystem;
+use ezlang::interpreter::*;
+use ezlang::time::VirtualClock;
+use ezlang::{run_source, STACK_SIZE};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(source) = std::str::from_utf8(data) else {
+        return;
+    };
+    let source = source.to_string();
+    // libFuzzer's thread has a small stack, run on one as large as the `ezlang` binary's
+    std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_stack_size(STACK_SIZE);
+            // `time.sleep` must not stall the fuzzer
+            interpreter.set_clock(Box::new(VirtualClock::new()));
+            // Programs may not touch the files of the machine running the fuzzer
+            interpreter.set_file_system(Box::new(DeniedFileSystem));
+            let _ = run_source(&mut interpreter, &source, false);
+        })
+        .expect("failed to spawn the interpreter thread")
+        .join()
+        .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+});
@@ -0,0 +1,19 @@
+#![no_main]
+
+use ezlang::parser::*;
+use ezlang::tokenizer::*;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(source) = std::str::from_utf8(data) else {
+        return;
+    };
+    let mut parser = Parser::new(Tokenizer::new(source));
+    // Every statement consumes at least one token
+    for _ in 0..=source.len() {
+        match parser.parse() {
+            Ok(Some(_)) => {}
+            Ok(None) | Err(_) => break,
+        }
+    }
+});
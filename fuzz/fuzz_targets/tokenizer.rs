@@ -0,0 +1,23 @@
+#![no_main]
+
+use ezlang::tokenizer::*;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(source) = std::str::from_utf8(data) else {
+        return;
+    };
+    let mut tokenizer = Tokenizer::new(source);
+    // Every token consumes at least one character, so the source must end in time
+    let mut finished = false;
+    for _ in 0..=source.len() {
+        match tokenizer.next_token() {
+            Ok(Token::EOF) | Err(_) => {
+                finished = true;
+                break;
+            }
+            Ok(_) => {}
+        }
+    }
+    assert!(finished, "tokenizer made no progress on {:?}", source);
+});
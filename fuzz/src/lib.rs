@@ -0,0 +1,197 @@
+// Grammar-aware program generator for the fuzz targets.
+//
+// The input bytes drive every choice, so libFuzzer's mutations map onto small
+// changes of the generated program. Once the bytes run out every choice
+// defaults to zero, which always picks the simplest alternative.
+
+const VARIABLES: [&str; 4] = ["a", "b", "count", "name"];
+const INTEGERS: [&str; 11] = [
+    "0",
+    "1",
+    "2",
+    "7",
+    "10",
+    "9223372036854775807",
+    "4611686018427387904",
+    "99999999999999999999",
+    "0xFF",
+    "0b1_0",
+    "1__0",
+];
+const FLOATS: [&str; 7] = ["0.0", "1.5", "2.25", "1.2.3", "1e-3", ".5", "1e"];
+const STRINGS: [&str; 10] = [
+    "\"\"",
+    "\"text\"",
+    "\"héllo wörld\"",
+    "\"unterminated",
+    "'single\\t\\u{e9}'",
+    "r\"raw\\n\"",
+    "\"\"\"triple\nquoted\"\"\"",
+    "\"bad \\q\"",
+    "f\"{a} and {count:>4}\"",
+    "f\"{b:.2f} {name[0]:q}\"",
+];
+const OPERATORS: [&str; 24] = [
+    "+", "-", "*", "/", "%", "and", "or", "&", "|", "==", "!=", "is", "<", ">", "<=", ">=", "in",
+    "**", "//", "^", "<<", ">>", "not in", "is not",
+];
+const METHODS: [&str; 6] = ["upper()", "strip()", "split()", "find(\"l\")", "len()", "format(1)"];
+const MAX_DEPTH: usize = 6;
+const MAX_LINES: usize = 40;
+
+struct Choices<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Choices<'a> {
+    fn next(&mut self, options: usize) -> usize {
+        let byte = self.data.get(self.position).copied().unwrap_or(0);
+        self.position += 1;
+        byte as usize % options
+    }
+
+    fn exhausted(&self) -> bool {
+        self.position >= self.data.len()
+    }
+}
+
+pub fn generate_program(data: &[u8]) -> String {
+    let mut choices = Choices { data, position: 0 };
+    let mut lines = Vec::new();
+    let mut indent = 0;
+
+    while !choices.exhausted() && lines.len() < MAX_LINES {
+        // Occasionally close blocks or produce an unexpected indent
+        match choices.next(8) {
+            0 if indent >= 4 => indent -= 4,
+            1 => indent = 0,
+            2 => indent += 1,
+            _ => {}
+        }
+        let (line, opens_block) = statement(&mut choices);
+        lines.push(format!("{}{}", " ".repeat(indent), line));
+        if opens_block {
+            indent += 4;
+        }
+    }
+    lines.join("\n")
+}
+
+fn statement(choices: &mut Choices) -> (String, bool) {
+    match choices.next(13) {
+        0 => (
+            format!("{} = {}", VARIABLES[choices.next(VARIABLES.len())], expression(choices, 0)),
+            false,
+        ),
+        1 => (
+            match choices.next(4) {
+                0 => format!(
+                    "{} {}= {}",
+                    VARIABLES[choices.next(VARIABLES.len())],
+                    OPERATORS[choices.next(5)],
+                    expression(choices, 0)
+                ),
+                1 => format!("a, b = b, {}", expression(choices, 0)),
+                2 => format!(
+                    "{}[{}] = {}",
+                    VARIABLES[choices.next(VARIABLES.len())],
+                    expression(choices, 0),
+                    expression(choices, 0)
+                ),
+                _ => format!("a, b = {}", expression(choices, 0)),
+            },
+            false,
+        ),
+        2 | 3 => (format!("print({})", expression(choices, 0)), false),
+        4 => (format!("if {}:", expression(choices, 0)), true),
+        5 => (format!("elseif {}:", expression(choices, 0)), true),
+        6 => (String::from("else:"), true),
+        7 => (format!("assert {}, \"message\"", expression(choices, 0)), false),
+        8 => (format!("test \"case {}\":", choices.next(3)), true),
+        9 => (format!("match {}:", expression(choices, 0)), true),
+        10 => (
+            format!(
+                "case {}:",
+                ["_", "1", "-2", "\"text\"", "0..10", "1 | 2", "[a, *b]", "[]", "a if a > 1"]
+                    [choices.next(9)]
+            ),
+            true,
+        ),
+        11 => match choices.next(6) {
+            0 => (String::from("try:"), true),
+            1 => (String::from("except:"), true),
+            2 => (String::from("except (ValueError, ZeroDivisionError) as e:"), true),
+            3 => (String::from("finally:"), true),
+            4 => (format!("raise ValueError({})", expression(choices, 0)), false),
+            _ => (String::from("raise"), false),
+        },
+        12 => match choices.next(7) {
+            0 => (String::from("def f(a, b):"), true),
+            1 => (String::from("class Point:"), true),
+            2 => (String::from("def __init__(self, a):"), true),
+            3 => (format!("return {}", expression(choices, 0)), false),
+            4 => (format!("self.a = {}", expression(choices, 0)), false),
+            5 => (
+                format!(
+                    "def {}(self, a):",
+                    ["__add__", "__eq__", "__lt__", "__getitem__", "__contains__"][choices.next(5)]
+                ),
+                true,
+            ),
+            _ => (
+                ["f(1, 2)", "Point(1).a", "super().f()", "Point(2)", "Point(1) + 1", "Point(1)[0]"]
+                    [choices.next(6)]
+                    .to_string(),
+                false,
+            ),
+        },
+        _ => (expression(choices, 0), false),
+    }
+}
+
+fn expression(choices: &mut Choices, depth: usize) -> String {
+    if depth >= MAX_DEPTH {
+        return literal(choices);
+    }
+    match choices.next(12) {
+        0 | 1 => literal(choices),
+        2 => VARIABLES[choices.next(VARIABLES.len())].to_string(),
+        3 => format!("({})", expression(choices, depth + 1)),
+        4 => format!("not {}", expression(choices, depth + 1)),
+        5 => format!("[{}, {}]", expression(choices, depth + 1), expression(choices, depth + 1)),
+        6 => format!(
+            "{}[{}:{}]",
+            expression(choices, depth + 1),
+            expression(choices, depth + 1),
+            expression(choices, depth + 1)
+        ),
+        7 => format!("{}.{}", expression(choices, depth + 1), METHODS[choices.next(METHODS.len())]),
+        9 => format!(
+            "{} if {} else {}",
+            expression(choices, depth + 1),
+            expression(choices, depth + 1),
+            expression(choices, depth + 1)
+        ),
+        8 => format!(
+            "{}{}",
+            ["-", "+", "~"][choices.next(3)],
+            expression(choices, depth + 1)
+        ),
+        _ => format!(
+            "{} {} {}",
+            expression(choices, depth + 1),
+            OPERATORS[choices.next(OPERATORS.len())],
+            expression(choices, depth + 1)
+        ),
+    }
+}
+
+fn literal(choices: &mut Choices) -> String {
+    match choices.next(4) {
+        0 => INTEGERS[choices.next(INTEGERS.len())].to_string(),
+        1 => FLOATS[choices.next(FLOATS.len())].to_string(),
+        2 => STRINGS[choices.next(STRINGS.len())].to_string(),
+        _ => ["true", "false"][choices.next(2)].to_string(),
+    }
+}
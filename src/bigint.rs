@@ -0,0 +1,494 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+// Arbitrary-precision integer used once a value no longer fits into i64.
+// The magnitude is stored as base 2^32 limbs, least significant first, without
+// leading zero limbs. Zero has no limbs and is never negative.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigInt {
+    negative: bool,
+    limbs: Vec<u32>,
+}
+
+const DECIMAL_CHUNK: u32 = 1_000_000_000;
+
+impl BigInt {
+    pub fn from_i64(value: i64) -> Self {
+        let mut magnitude = value.unsigned_abs();
+        let mut limbs = Vec::new();
+        while magnitude > 0 {
+            limbs.push(magnitude as u32);
+            magnitude >>= 32;
+        }
+        BigInt {
+            negative: value < 0,
+            limbs,
+        }
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.limbs.len() > 2 {
+            return None;
+        }
+        let mut magnitude: u64 = 0;
+        for (index, limb) in self.limbs.iter().enumerate() {
+            magnitude |= (*limb as u64) << (32 * index);
+        }
+        if self.negative {
+            if magnitude == i64::MIN.unsigned_abs() {
+                Some(i64::MIN)
+            } else {
+                i64::try_from(magnitude).ok().map(|value| -value)
+            }
+        } else {
+            i64::try_from(magnitude).ok()
+        }
+    }
+
+    // Nearest float, ties to even like the conversion of a u64
+    pub fn to_f64(&self) -> f64 {
+        let length = self.bit_length();
+        // The top 64 bits, the lowest of them set if any bit below was lost. Only 53
+        // bits fit into the float, so that bit still decides how to round.
+        let shift = length.saturating_sub(64);
+        let mut top: u64 = 0;
+        for bit in (shift..length).rev() {
+            top = top << 1 | self.bit(bit) as u64;
+        }
+        if (0..shift).any(|bit| self.bit(bit)) {
+            top |= 1;
+        }
+        let value = scale(top as f64, shift as i64);
+        if self.negative {
+            -value
+        } else {
+            value
+        }
+    }
+
+    // Integer part of a finite float, None for infinity and NaN
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+        if value.abs() < 9.0e18 {
+            return Some(BigInt::from_i64(value as i64));
+        }
+        // Beyond 2^53 every float is an integer, its mantissa shifted left
+        let bits = value.to_bits();
+        let mantissa = (bits & ((1 << 52) - 1)) | (1 << 52);
+        let exponent = ((bits >> 52) & 0x7ff) as usize - 1075;
+        let magnitude = BigInt::from_i64(mantissa as i64).shl(exponent);
+        Some(if value < 0.0 { magnitude.neg() } else { magnitude })
+    }
+
+    fn bit(&self, index: usize) -> bool {
+        self.limbs[index / 32] >> (index % 32) & 1 == 1
+    }
+
+    // Parses an optionally signed string of decimal digits
+    pub fn parse(text: &str) -> Option<Self> {
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+        let mut limbs = Vec::new();
+        for chunk in digits.as_bytes().chunks(9) {
+            let mut chunk_value = 0;
+            for byte in chunk {
+                chunk_value = chunk_value * 10 + (byte - b'0') as u32;
+            }
+            mul_small_add(&mut limbs, 10u32.pow(chunk.len() as u32), chunk_value);
+        }
+        Some(BigInt::new(negative, limbs))
+    }
+
+    // Parses unsigned digits in base 2 to 16
+    pub fn parse_radix(digits: &str, radix: u32) -> Option<Self> {
+        if digits.is_empty() {
+            return None;
+        }
+        let mut limbs = Vec::new();
+        for c in digits.chars() {
+            mul_small_add(&mut limbs, radix, c.to_digit(radix)?);
+        }
+        Some(BigInt::new(false, limbs))
+    }
+
+    pub fn bit_length(&self) -> usize {
+        match self.limbs.last() {
+            Some(top) => self.limbs.len() * 32 - top.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn add(&self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(self.negative, add_magnitudes(&self.limbs, &other.limbs));
+        }
+        match compare_magnitudes(&self.limbs, &other.limbs) {
+            Ordering::Less => {
+                BigInt::new(other.negative, sub_magnitudes(&other.limbs, &self.limbs))
+            }
+            _ => BigInt::new(self.negative, sub_magnitudes(&self.limbs, &other.limbs)),
+        }
+    }
+
+    pub fn sub(&self, other: &BigInt) -> BigInt {
+        self.add(&other.neg())
+    }
+
+    pub fn mul(&self, other: &BigInt) -> BigInt {
+        BigInt::new(
+            self.negative != other.negative,
+            mul_magnitudes(&self.limbs, &other.limbs),
+        )
+    }
+
+    // Truncating division, the remainder takes the sign of the dividend.
+    // Returns None for a zero divisor.
+    pub fn div_rem(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+        if other.is_zero() {
+            return None;
+        }
+        let (quotient, remainder) = div_rem_magnitudes(&self.limbs, &other.limbs);
+        Some((
+            BigInt::new(self.negative != other.negative, quotient),
+            BigInt::new(self.negative, remainder),
+        ))
+    }
+
+    // Quotient rounded once to the nearest float, infinite when it is too large.
+    // Like Python, the dividend is scaled so the integer quotient has 55 or 56 bits,
+    // a nonzero remainder is kept as a sticky bit and the quotient is rounded half to
+    // even to the 53 bits of a float, or fewer for a subnormal result.
+    // Returns None for a zero divisor.
+    pub fn true_div(&self, other: &BigInt) -> Option<f64> {
+        if other.is_zero() {
+            return None;
+        }
+        let negative = self.negative != other.negative;
+        let difference = self.bit_length() as i64 - other.bit_length() as i64;
+        let magnitude = if self.is_zero() || difference < -1075 {
+            0.0
+        } else if difference > 1025 {
+            f64::INFINITY
+        } else {
+            let shift = difference.max(-1021) - 55;
+            let numerator = BigInt::new(false, self.limbs.clone());
+            let denominator = BigInt::new(false, other.limbs.clone());
+            let (numerator, denominator) = if shift >= 0 {
+                (numerator, denominator.shl(shift as usize))
+            } else {
+                (numerator.shl(shift.unsigned_abs() as usize), denominator)
+            };
+            let (quotient, remainder) = div_rem_magnitudes(&numerator.limbs, &denominator.limbs);
+            let mut bits = quotient
+                .iter()
+                .take(2)
+                .enumerate()
+                .fold(0u64, |bits, (index, limb)| bits | (*limb as u64) << (32 * index));
+            let length = 64 - bits.leading_zeros() as i64;
+            let extra = length.max(-1021 - shift) - 53;
+            // `half` is the highest dropped bit, round up above it or to an even result
+            let half = 1u64 << (extra - 1);
+            let inexact = remainder.iter().any(|limb| *limb != 0);
+            let dropped = bits & (2 * half - 1) | u64::from(inexact);
+            if dropped & half != 0 && dropped & (3 * half - 1) != 0 {
+                bits += half;
+            }
+            bits &= !(2 * half - 1);
+            scale(bits as f64, shift)
+        };
+        Some(if negative { -magnitude } else { magnitude })
+    }
+
+    pub fn neg(&self) -> BigInt {
+        BigInt::new(!self.negative, self.limbs.clone())
+    }
+
+    pub fn is_odd(&self) -> bool {
+        self.limbs.first().is_some_and(|limb| limb & 1 == 1)
+    }
+
+    // Bitwise operations behave as if the numbers were in infinite two's complement
+    pub fn not(&self) -> BigInt {
+        self.neg().sub(&BigInt::from_i64(1))
+    }
+
+    pub fn bitand(&self, other: &BigInt) -> BigInt {
+        self.bitwise(other, |left, right| left & right)
+    }
+
+    pub fn bitor(&self, other: &BigInt) -> BigInt {
+        self.bitwise(other, |left, right| left | right)
+    }
+
+    pub fn bitxor(&self, other: &BigInt) -> BigInt {
+        self.bitwise(other, |left, right| left ^ right)
+    }
+
+    pub fn shl(&self, bits: usize) -> BigInt {
+        let mut limbs = vec![0u32; bits / 32];
+        let shift = bits % 32;
+        let mut carry = 0u32;
+        for limb in &self.limbs {
+            if shift == 0 {
+                limbs.push(*limb);
+            } else {
+                limbs.push((limb << shift) | carry);
+                carry = limb >> (32 - shift);
+            }
+        }
+        limbs.push(carry);
+        BigInt::new(self.negative, limbs)
+    }
+
+    // Rounds towards negative infinity like floor division by a power of two
+    pub fn shr(&self, bits: usize) -> BigInt {
+        if bits >= self.bit_length() {
+            return BigInt::from_i64(if self.negative { -1 } else { 0 });
+        }
+        let shift = bits % 32;
+        let source = &self.limbs[bits / 32..];
+        let mut limbs = Vec::with_capacity(source.len());
+        for (index, limb) in source.iter().enumerate() {
+            let high = source.get(index + 1).copied().unwrap_or(0);
+            if shift == 0 {
+                limbs.push(*limb);
+            } else {
+                limbs.push((limb >> shift) | (high << (32 - shift)));
+            }
+        }
+        let shifted = BigInt::new(self.negative, limbs);
+        // Truncation rounded a negative number towards zero if bits were lost
+        if self.negative && shifted.shl(bits) != *self {
+            shifted.sub(&BigInt::from_i64(1))
+        } else {
+            shifted
+        }
+    }
+
+    fn bitwise(&self, other: &BigInt, operation: fn(u32, u32) -> u32) -> BigInt {
+        let length = self.limbs.len().max(other.limbs.len()) + 1;
+        let left = self.twos_complement(length);
+        let right = other.twos_complement(length);
+        let limbs: Vec<u32> = left
+            .iter()
+            .zip(&right)
+            .map(|(left, right)| operation(*left, *right))
+            .collect();
+        // The top bit is the sign of the result
+        if limbs[length - 1] >> 31 == 1 {
+            let inverted: Vec<u32> = limbs.iter().map(|limb| !limb).collect();
+            BigInt::new(true, add_magnitudes(&inverted, &[1]))
+        } else {
+            BigInt::new(false, limbs)
+        }
+    }
+
+    fn twos_complement(&self, length: usize) -> Vec<u32> {
+        let mut limbs = if self.negative {
+            // -m is the inverse of m - 1
+            sub_magnitudes(&self.limbs, &[1])
+        } else {
+            self.limbs.clone()
+        };
+        limbs.resize(length, 0);
+        if self.negative {
+            for limb in limbs.iter_mut() {
+                *limb = !*limb;
+            }
+        }
+        limbs
+    }
+
+    fn new(negative: bool, mut limbs: Vec<u32>) -> Self {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        BigInt {
+            negative: negative && !limbs.is_empty(),
+            limbs,
+        }
+    }
+}
+
+// `value * 2^exponent`, exact whenever the result is a float
+fn scale(mut value: f64, mut exponent: i64) -> f64 {
+    // Powers of two from 2^-1000 to 2^1000 are normal floats
+    let power = |exponent: i64| f64::from_bits(((exponent + 1023) as u64) << 52);
+    while exponent > 1000 {
+        value *= power(1000);
+        exponent -= 1000;
+    }
+    while exponent < -1000 {
+        value *= power(-1000);
+        exponent += 1000;
+    }
+    value * power(exponent)
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_magnitudes(&self.limbs, &other.limbs),
+            (true, true) => compare_magnitudes(&other.limbs, &self.limbs),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        // Split off groups of nine decimal digits, least significant first
+        let mut chunks = Vec::new();
+        let mut magnitude = self.limbs.clone();
+        while !magnitude.is_empty() {
+            chunks.push(div_small(&mut magnitude, DECIMAL_CHUNK));
+        }
+        if self.negative {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", chunks.pop().unwrap_or(0))?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+fn compare_magnitudes(left: &[u32], right: &[u32]) -> Ordering {
+    left.len()
+        .cmp(&right.len())
+        .then_with(|| left.iter().rev().cmp(right.iter().rev()))
+}
+
+fn add_magnitudes(left: &[u32], right: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(left.len().max(right.len()) + 1);
+    let mut carry = 0u64;
+    for index in 0..left.len().max(right.len()) {
+        let sum = *left.get(index).unwrap_or(&0) as u64 + *right.get(index).unwrap_or(&0) as u64 + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    if carry > 0 {
+        result.push(carry as u32);
+    }
+    result
+}
+
+// Requires left >= right
+fn sub_magnitudes(left: &[u32], right: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(left.len());
+    let mut borrow = 0i64;
+    for (index, limb) in left.iter().enumerate() {
+        let mut difference = *limb as i64 - *right.get(index).unwrap_or(&0) as i64 - borrow;
+        if difference < 0 {
+            difference += 1 << 32;
+            borrow = 1;
+        } else {
+            borrow = 0;
+        }
+        result.push(difference as u32);
+    }
+    result
+}
+
+fn mul_magnitudes(left: &[u32], right: &[u32]) -> Vec<u32> {
+    let mut result = vec![0u32; left.len() + right.len()];
+    for (i, left_limb) in left.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, right_limb) in right.iter().enumerate() {
+            let product = *left_limb as u64 * *right_limb as u64 + result[i + j] as u64 + carry;
+            result[i + j] = product as u32;
+            carry = product >> 32;
+        }
+        result[i + right.len()] = carry as u32;
+    }
+    result
+}
+
+fn mul_small_add(limbs: &mut Vec<u32>, factor: u32, addend: u32) {
+    let mut carry = addend as u64;
+    for limb in limbs.iter_mut() {
+        let product = *limb as u64 * factor as u64 + carry;
+        *limb = product as u32;
+        carry = product >> 32;
+    }
+    if carry > 0 {
+        limbs.push(carry as u32);
+    }
+}
+
+// Divides in place and returns the remainder
+fn div_small(limbs: &mut Vec<u32>, divisor: u32) -> u32 {
+    let mut remainder = 0u64;
+    for limb in limbs.iter_mut().rev() {
+        let current = (remainder << 32) | *limb as u64;
+        *limb = (current / divisor as u64) as u32;
+        remainder = current % divisor as u64;
+    }
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+    remainder as u32
+}
+
+fn div_rem_magnitudes(left: &[u32], right: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if compare_magnitudes(left, right) == Ordering::Less {
+        return (Vec::new(), left.to_vec());
+    }
+    if right.len() == 1 {
+        let mut quotient = left.to_vec();
+        let remainder = div_small(&mut quotient, right[0]);
+        return (quotient, vec![remainder]);
+    }
+    // Binary long division, one bit of the dividend at a time
+    let mut quotient = vec![0u32; left.len()];
+    let mut remainder: Vec<u32> = Vec::new();
+    for bit in (0..left.len() * 32).rev() {
+        shift_left_one(&mut remainder, (left[bit / 32] >> (bit % 32)) & 1);
+        if compare_magnitudes(&remainder, right) != Ordering::Less {
+            remainder = sub_magnitudes(&remainder, right);
+            while remainder.last() == Some(&0) {
+                remainder.pop();
+            }
+            quotient[bit / 32] |= 1 << (bit % 32);
+        }
+    }
+    (quotient, remainder)
+}
+
+fn shift_left_one(limbs: &mut Vec<u32>, low_bit: u32) {
+    let mut carry = low_bit;
+    for limb in limbs.iter_mut() {
+        let next_carry = *limb >> 31;
+        *limb = (*limb << 1) | carry;
+        carry = next_carry;
+    }
+    if carry > 0 {
+        limbs.push(carry);
+    }
+}
@@ -0,0 +1,226 @@
+use std::fmt;
+use std::rc::Rc;
+
+use crate::interpreter::type_name;
+use crate::objects::Class;
+use crate::tokenizer::Token;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EzError {
+    pub code: &'static str,
+    pub kind: String,
+    pub message: String,
+    pub line: usize,
+    // Innermost frame first
+    pub trace: Vec<Frame>,
+    // Class of an error created from a user-defined error type
+    pub class: Option<Rc<Class>>,
+}
+
+// Statement an error passed through on its way out, `source` is the text of its line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub function: String,
+    pub file: String,
+    pub line: usize,
+    // Column of the token a syntax error was found at, or where the statement starts
+    pub column: usize,
+    pub source: String,
+    // Call depth, every function call adds one frame
+    pub depth: usize,
+}
+
+impl EzError {
+    pub fn new(code: &'static str, message: String) -> Self {
+        EzError {
+            code,
+            kind: kind_of(code).to_string(),
+            message,
+            line: 0,
+            trace: Vec::new(),
+            class: None,
+        }
+    }
+
+    // Error created by the program, `ValueError("bad input")` or a user type like `NetworkError("down")`
+    pub fn raised(kind: &str, message: String) -> Self {
+        EzError {
+            code: code_of(kind),
+            kind: kind.to_string(),
+            message,
+            line: 0,
+            trace: Vec::new(),
+            class: None,
+        }
+    }
+
+    // Error created from a class, it has the code of the built-in error type the class derives from
+    pub fn of_class(class: &Rc<Class>, message: String) -> Self {
+        EzError {
+            code: code_of(class.error.as_deref().unwrap_or("Exception")),
+            kind: class.name.clone(),
+            message,
+            line: 0,
+            trace: Vec::new(),
+            class: Some(class.clone()),
+        }
+    }
+
+    // Whether an `except` clause naming this built-in type catches the error, errors of a
+    // class are caught by the built-in type it derives from
+    pub fn is_kind(&self, kind: &str) -> bool {
+        kind == "Exception"
+            || match &self.class {
+                Some(class) => class.error.as_deref() == Some(kind),
+                None => self.kind == kind,
+            }
+    }
+
+    // Whether an `except` clause naming this error class catches the error
+    pub fn is_instance(&self, class: &Rc<Class>) -> bool {
+        self.class.as_ref().is_some_and(|own| own.derives_from(class))
+    }
+
+    pub fn with_line(mut self, line: usize) -> Self {
+        // Keep the innermost line if one is already known
+        if self.line == 0 {
+            self.line = line;
+        }
+        self
+    }
+
+    pub fn with_frame(mut self, frame: Frame) -> Self {
+        // A caught and re-raised error keeps the frame where it was first raised
+        if !matches!(self.trace.last(), Some(last) if last.depth == frame.depth) {
+            self.trace.push(frame);
+        }
+        self
+    }
+
+    // The error with its frames, outermost first, as an uncaught error is reported
+    pub fn traceback(&self) -> String {
+        let mut text = String::new();
+        if !self.trace.is_empty() {
+            text.push_str("Traceback (most recent call last):\n");
+        }
+        // Like in Python, recursion shows a frame at most three times in a row
+        let mut repeated = 0;
+        let mut previous: Option<&Frame> = None;
+        for frame in self.trace.iter().rev() {
+            if previous.is_some_and(|previous| previous.same_place(frame)) {
+                repeated += 1;
+            } else {
+                push_repeated(&mut text, repeated);
+                repeated = 0;
+            }
+            previous = Some(frame);
+            if repeated >= 3 {
+                continue;
+            }
+            text.push_str(&format!(
+                "  File \"{}\", line {}, column {}, in {}\n",
+                frame.file, frame.line, frame.column, frame.function
+            ));
+            if !frame.source.is_empty() {
+                text.push_str(&format!("    {}\n", frame.source));
+            }
+        }
+        push_repeated(&mut text, repeated);
+        text.push_str(&self.to_string());
+        text
+    }
+}
+
+impl Frame {
+    fn same_place(&self, other: &Frame) -> bool {
+        self.file == other.file
+            && self.line == other.line
+            && self.column == other.column
+            && self.function == other.function
+    }
+}
+
+fn push_repeated(text: &mut String, repeated: usize) {
+    if repeated > 2 {
+        text.push_str(&format!(
+            "  [Previous line repeated {} more times]\n",
+            repeated - 2
+        ));
+    }
+}
+
+// Language-level error type for every error code
+fn kind_of(code: &str) -> &'static str {
+    match code {
+        "i002" => "NameError",
+        "i010" => "IndentationError",
+        "i012" => "AssertionError",
+        "i013" => "OverflowError",
+        "i014" => "ZeroDivisionError",
+        "i015" => "IndexError",
+        "i016" => "AttributeError",
+        "i018" => "ValueError",
+        "i019" => "SyntaxError",
+        "i020" => "RuntimeError",
+        "i021" => "RecursionError",
+        "i022" => "ImportError",
+        "i023" => "OSError",
+        _ if code.starts_with('t') || code.starts_with('p') => "SyntaxError",
+        _ => "TypeError",
+    }
+}
+
+// Code of a built-in error type, user-defined types share i020
+fn code_of(kind: &str) -> &'static str {
+    match kind {
+        "NameError" => "i002",
+        "TypeError" => "i003",
+        "IndentationError" => "i010",
+        "AssertionError" => "i012",
+        "OverflowError" => "i013",
+        "ZeroDivisionError" => "i014",
+        "IndexError" => "i015",
+        "AttributeError" => "i016",
+        "ValueError" => "i018",
+        "SyntaxError" => "i019",
+        "RecursionError" => "i021",
+        "ImportError" => "i022",
+        "OSError" => "i023",
+        _ => "i020",
+    }
+}
+
+// TypeError of a call with the wrong number of arguments, `count` is the accepted
+// number like "2" or "1 or 2"
+pub(crate) fn argument_count(function: &str, count: &str, arguments: &[Token]) -> EzError {
+    EzError::new(
+        "i017",
+        format!(
+            "{} takes {} arguments but {} were given",
+            function,
+            count,
+            arguments.len()
+        ),
+    )
+}
+
+// TypeError of a call given an argument of the wrong type, `kind` is the accepted one
+pub(crate) fn expected(function: &str, kind: &str, value: &Token) -> EzError {
+    EzError::new(
+        "i003",
+        format!("{} expects {}, got {}", function, kind, type_name(value)),
+    )
+}
+
+impl fmt::Display for EzError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Error {} ({}): {}", self.code, self.kind, self.message)?;
+        // A traceback already names the line
+        if self.line > 0 && self.trace.is_empty() {
+            write!(f, " on line {}", self.line)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for EzError {}
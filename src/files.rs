@@ -0,0 +1,396 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::fs;
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::error::{argument_count, expected, EzError};
+use crate::tokenizer::Token;
+
+// Functions of the built-in `files` module
+pub const FUNCTIONS: &[&str] = &[
+    "read_text", "write_text", "append_text", "exists", "is_dir", "list", "remove", "join",
+    "split",
+];
+
+// Every file access of the interpreter goes through this trait, so an embedding
+// host can sandbox the program or give it a file system of its own
+pub trait FileSystem: fmt::Debug {
+    fn read(&mut self, path: &str) -> io::Result<String>;
+    // Replaces the text of the file, or adds to its end when `append` is set.
+    // Missing files are created.
+    fn write(&mut self, path: &str, text: &str, append: bool) -> io::Result<()>;
+    // Names of the entries of a directory
+    fn list(&mut self, path: &str) -> io::Result<Vec<String>>;
+    fn remove(&mut self, path: &str) -> io::Result<()>;
+    fn exists(&mut self, path: &str) -> bool;
+    fn is_dir(&mut self, path: &str) -> bool;
+    // Absolute form of the path, imports of the same file under two paths share the module
+    fn canonicalize(&mut self, path: &str) -> io::Result<String>;
+}
+
+// Files of the machine, relative paths start in the working directory
+#[derive(Debug, Default)]
+pub struct SystemFileSystem;
+
+impl FileSystem for SystemFileSystem {
+    fn read(&mut self, path: &str) -> io::Result<String> {
+        if Path::new(path).is_dir() {
+            return Err(is_a_directory());
+        }
+        fs::read_to_string(path)
+    }
+
+    fn write(&mut self, path: &str, text: &str, append: bool) -> io::Result<()> {
+        if Path::new(path).is_dir() {
+            return Err(is_a_directory());
+        }
+        fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)?
+            .write_all(text.as_bytes())
+    }
+
+    fn list(&mut self, path: &str) -> io::Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in fs::read_dir(path)? {
+            names.push(entry?.file_name().to_string_lossy().into_owned());
+        }
+        Ok(names)
+    }
+
+    fn remove(&mut self, path: &str) -> io::Result<()> {
+        if Path::new(path).is_dir() {
+            return Err(is_a_directory());
+        }
+        fs::remove_file(path)
+    }
+
+    fn exists(&mut self, path: &str) -> bool {
+        Path::new(path).exists()
+    }
+
+    fn is_dir(&mut self, path: &str) -> bool {
+        Path::new(path).is_dir()
+    }
+
+    fn canonicalize(&mut self, path: &str) -> io::Result<String> {
+        Ok(fs::canonicalize(path)?.to_string_lossy().into_owned())
+    }
+}
+
+// No files at all, every access fails as if permission was denied
+#[derive(Debug, Default)]
+pub struct DeniedFileSystem;
+
+impl FileSystem for DeniedFileSystem {
+    fn read(&mut self, _path: &str) -> io::Result<String> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn write(&mut self, _path: &str, _text: &str, _append: bool) -> io::Result<()> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn list(&mut self, _path: &str) -> io::Result<Vec<String>> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn remove(&mut self, _path: &str) -> io::Result<()> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn exists(&mut self, _path: &str) -> bool {
+        false
+    }
+
+    fn is_dir(&mut self, _path: &str) -> bool {
+        false
+    }
+
+    fn canonicalize(&mut self, _path: &str) -> io::Result<String> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+}
+
+fn is_a_directory() -> io::Error {
+    io::Error::other("Is a directory")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Read,
+    Write,
+    Append,
+}
+
+impl Mode {
+    fn letter(self) -> &'static str {
+        match self {
+            Mode::Read => "r",
+            Mode::Write => "w",
+            Mode::Append => "a",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct OpenFile {
+    path: String,
+    mode: Mode,
+    // Text of a file opened for reading and the byte offset read up to
+    text: String,
+    position: usize,
+    closed: bool,
+}
+
+// File returned by `open`, copies of the value share the file
+#[derive(Debug, Clone)]
+pub struct File(Rc<RefCell<OpenFile>>);
+
+impl PartialEq for File {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Display for File {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let file = self.0.borrow();
+        write!(f, "<file '{}' mode '{}'>", file.path, file.mode.letter())
+    }
+}
+
+impl File {
+    pub fn close(&self) {
+        let mut file = self.0.borrow_mut();
+        file.closed = true;
+        file.text = String::new();
+    }
+
+    fn reading(&self) -> Result<std::cell::RefMut<'_, OpenFile>, EzError> {
+        let file = self.open_file()?;
+        if file.mode != Mode::Read {
+            return Err(EzError::new("i023", String::from("File not open for reading")));
+        }
+        Ok(file)
+    }
+
+    fn open_file(&self) -> Result<std::cell::RefMut<'_, OpenFile>, EzError> {
+        let file = self.0.borrow_mut();
+        if file.closed {
+            return Err(EzError::new("i018", String::from("I/O operation on closed file")));
+        }
+        Ok(file)
+    }
+}
+
+// `open(path)` reads the whole file at once, `open(path, "w")` empties it and
+// `open(path, "a")` writes after its end. Writes go to the file right away.
+pub fn open(files: &mut dyn FileSystem, arguments: &[Token]) -> Result<Token, EzError> {
+    let (path, mode) = match arguments {
+        [Token::String(path)] => (path, "r"),
+        [Token::String(path), Token::String(mode)] => (path, mode.as_str()),
+        [Token::String(_), value] => return Err(expected("open", "a string mode", value)),
+        [value] | [value, _] => return Err(expected("open", "a string path", value)),
+        _ => return Err(argument_count("open", "1 or 2", arguments)),
+    };
+    let (mode, text) = match mode {
+        "r" => (Mode::Read, files.read(path).map_err(|error| io_error(error, path))?),
+        "w" | "a" => {
+            let append = mode == "a";
+            files
+                .write(path, "", append)
+                .map_err(|error| io_error(error, path))?;
+            (if append { Mode::Append } else { Mode::Write }, String::new())
+        }
+        _ => {
+            return Err(EzError::new(
+                "i018",
+                format!("Invalid file mode: {}", mode),
+            ))
+        }
+    };
+    Ok(Token::File(File(Rc::new(RefCell::new(OpenFile {
+        path: path.clone(),
+        mode,
+        text,
+        position: 0,
+        closed: false,
+    })))))
+}
+
+pub fn call(files: &mut dyn FileSystem, function: &str, arguments: &[Token]) -> Result<Token, EzError> {
+    match (function, arguments) {
+        ("read_text", [Token::String(path)]) => files
+            .read(path)
+            .map(Token::String)
+            .map_err(|error| io_error(error, path)),
+        ("write_text" | "append_text", [Token::String(path), Token::String(text)]) => {
+            files
+                .write(path, text, function == "append_text")
+                .map_err(|error| io_error(error, path))?;
+            Ok(Token::None)
+        }
+        ("write_text" | "append_text", [Token::String(_), value]) => {
+            Err(expected(function, "a string", value))
+        }
+        ("exists", [Token::String(path)]) => Ok(Token::Boolean(files.exists(path))),
+        ("is_dir", [Token::String(path)]) => Ok(Token::Boolean(files.is_dir(path))),
+        // Sorted so the listing is the same on every platform
+        ("list", []) => call(files, function, &[Token::String(String::from("."))]),
+        ("list", [Token::String(path)]) => {
+            let mut names = files.list(path).map_err(|error| io_error(error, path))?;
+            names.sort();
+            Ok(Token::List(names.into_iter().map(Token::String).collect()))
+        }
+        ("remove", [Token::String(path)]) => {
+            files.remove(path).map_err(|error| io_error(error, path))?;
+            Ok(Token::None)
+        }
+        // Joining an absolute path starts over from it
+        ("join", [_, ..]) => {
+            let mut path = PathBuf::new();
+            for part in arguments {
+                match part {
+                    Token::String(part) => path.push(part),
+                    _ => return Err(expected(function, "strings", part)),
+                }
+            }
+            Ok(Token::String(path.to_string_lossy().into_owned()))
+        }
+        // `split("data/notes.txt")` is ["data", "notes.txt"]
+        ("split", [Token::String(path)]) => {
+            let path = Path::new(path);
+            let parent = path.parent().map_or(String::new(), |parent| {
+                parent.to_string_lossy().into_owned()
+            });
+            let name = path
+                .file_name()
+                .map_or(String::new(), |name| name.to_string_lossy().into_owned());
+            Ok(Token::List(vec![Token::String(parent), Token::String(name)]))
+        }
+        ("write_text" | "append_text", [value, _])
+        | ("read_text" | "exists" | "is_dir" | "list" | "remove" | "split", [value]) => {
+            Err(expected(function, "a string path", value))
+        }
+        ("read_text" | "write_text" | "append_text" | "exists" | "is_dir" | "list" | "remove"
+        | "join" | "split", _) => Err(argument_count(
+            function,
+            match function {
+                "write_text" | "append_text" => "2",
+                "list" => "0 or 1",
+                "join" => "at least 1",
+                _ => "1",
+            },
+            arguments,
+        )),
+        _ => Err(EzError::new(
+            "i016",
+            format!("Module files has no attribute: {}", function),
+        )),
+    }
+}
+
+// Fields of a file, `handle.name`
+pub fn attribute(file: &File, name: &str) -> Option<Token> {
+    let file = file.0.borrow();
+    match name {
+        "name" => Some(Token::String(file.path.clone())),
+        "mode" => Some(Token::String(file.mode.letter().to_string())),
+        "closed" => Some(Token::Boolean(file.closed)),
+        _ => None,
+    }
+}
+
+pub fn call_method(
+    files: &mut dyn FileSystem,
+    file: &File,
+    method: &str,
+    arguments: Vec<Token>,
+) -> Result<Token, EzError> {
+    match (method, arguments.as_slice()) {
+        // The rest of the file, or at most `count` characters of it
+        ("read", []) => read(file, None),
+        ("read", [Token::Integer(count)]) => read(file, usize::try_from(*count).ok()),
+        // The next line with its newline, an empty string at the end of the file
+        ("readline", []) => {
+            let mut file = file.reading()?;
+            let rest = &file.text[file.position..];
+            let length = rest.find('\n').map_or(rest.len(), |index| index + 1);
+            let line = rest[..length].to_string();
+            file.position += length;
+            Ok(Token::String(line))
+        }
+        ("readlines", []) => {
+            let mut file = file.reading()?;
+            let lines: Vec<Token> = file.text[file.position..]
+                .split_inclusive('\n')
+                .map(|line| Token::String(line.to_string()))
+                .collect();
+            file.position = file.text.len();
+            Ok(Token::List(lines))
+        }
+        // Returns the number of characters written
+        ("write", [Token::String(text)]) => {
+            let file = file.open_file()?;
+            if file.mode == Mode::Read {
+                return Err(EzError::new("i023", String::from("File not open for writing")));
+            }
+            files
+                .write(&file.path, text, true)
+                .map_err(|error| io_error(error, &file.path))?;
+            Ok(Token::Integer(text.chars().count() as i64))
+        }
+        ("close", []) => {
+            file.close();
+            Ok(Token::None)
+        }
+        ("read" | "write", [value]) => Err(expected(
+            method,
+            if method == "read" { "an integer" } else { "a string" },
+            value,
+        )),
+        ("read" | "readline" | "readlines" | "write" | "close", _) => Err(argument_count(
+            method,
+            match method {
+                "read" => "0 or 1",
+                "write" => "1",
+                _ => "0",
+            },
+            &arguments,
+        )),
+        _ => Err(EzError::new(
+            "i016",
+            format!("File has no method: {}", method),
+        )),
+    }
+}
+
+// A negative or missing count reads to the end
+fn read(file: &File, count: Option<usize>) -> Result<Token, EzError> {
+    let mut file = file.reading()?;
+    let rest = &file.text[file.position..];
+    let length = count
+        .and_then(|count| rest.char_indices().nth(count))
+        .map_or(rest.len(), |(index, _)| index);
+    let text = rest[..length].to_string();
+    file.position += length;
+    Ok(Token::String(text))
+}
+
+// OSError naming the path, with the same reason on every platform where possible
+fn io_error(error: io::Error, path: &str) -> EzError {
+    let reason = match error.kind() {
+        ErrorKind::NotFound => String::from("No such file or directory"),
+        ErrorKind::PermissionDenied => String::from("Permission denied"),
+        ErrorKind::AlreadyExists => String::from("File exists"),
+        ErrorKind::InvalidData => String::from("File is not valid UTF-8"),
+        _ => error.to_string(),
+    };
+    EzError::new("i023", format!("{}: {}", reason, path))
+}
@@ -1,29 +1,978 @@
+use crate::bigint::BigInt;
+use crate::error::{argument_count, EzError, Frame};
+use crate::files::{self, FileSystem, SystemFileSystem};
+use crate::objects::*;
 use crate::parser::*;
+use crate::random::{self, Random};
+use crate::math;
+use crate::strings;
+use crate::time::{self, Clock, SystemClock};
 use crate::tokenizer::*;
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+// Largest integer result, keeps runaway computations from exhausting memory
+pub(crate) const MAX_INTEGER_BITS: usize = 1 << 17;
+// Longest string or list built by repetition or concatenation
+const MAX_SEQUENCE_LENGTH: usize = 1 << 24;
+// Deepest chain of function calls, keeps runaway recursion from overflowing the stack
+const MAX_CALL_DEPTH: usize = 1000;
+// Native stack of the thread running the interpreter unless told otherwise,
+// the size Rust gives to spawned threads
+const DEFAULT_STACK_SIZE: usize = 2 << 20;
+
+// Innermost `match` statements, their subject and whether an arm was taken
+#[derive(Debug)]
+struct MatchState {
+    indent_level: usize,
+    subject: Token,
+    matched: bool,
+}
+
+// Open `try` statements. `error` is raised in the body or an arm and not handled yet,
+// `handling` is the error caught by the running `except` arm for a bare `raise`.
+#[derive(Debug)]
+struct TryState {
+    indent_level: usize,
+    error: Option<EzError>,
+    handling: Option<EzError>,
+    arms_closed: bool,
+    in_finally: bool,
+}
+
+// `def` whose body lines are being collected
+#[derive(Debug)]
+struct Recording {
+    indent_level: usize,
+    name: String,
+    parameters: Vec<String>,
+    body: Vec<(ASTNode, usize)>,
+}
+
+// `class` whose body is running, the names it binds become the class attributes
+#[derive(Debug)]
+struct ClassState {
+    indent_level: usize,
+    name: String,
+    base: Option<Rc<Class>>,
+    error: Option<String>,
+    attributes: HashMap<String, Token>,
+}
+
+// Open `with` statements, the value is closed when the block ends
+#[derive(Debug)]
+struct WithState {
+    indent_level: usize,
+    line_number: usize,
+    value: Token,
+}
+
+// State of the caller, put aside while a function body runs
+struct CallState {
+    locals: Option<HashMap<String, Token>>,
+    line_number: usize,
+    pass_lock: bool,
+    indent_level: usize,
+    new_indent_level: bool,
+    matches: Vec<MatchState>,
+    tries: Vec<TryState>,
+    recording: Option<Recording>,
+    classes: Vec<ClassState>,
+    withs: Vec<WithState>,
+    returning: Option<Token>,
+    source: Source,
+    function: String,
+    method: Option<(Rc<Class>, Token)>,
+}
 
 #[derive(Debug)]
 pub struct Interpreter {
+    // Globals, function calls keep their variables in `locals`
     variables: HashMap<String, Token>,
+    locals: Option<HashMap<String, Token>>,
     line_number: usize,
     pass_lock: bool,
     indent_level: usize,
     new_indent_level: bool,
+    selected_test: Option<String>,
+    matches: Vec<MatchState>,
+    tries: Vec<TryState>,
+    recording: Option<Recording>,
+    classes: Vec<ClassState>,
+    withs: Vec<WithState>,
+    // Value of an executed `return`, the rest of the body is skipped
+    returning: Option<Token>,
+    source: Source,
+    function: String,
+    depth: usize,
+    // Expressions being evaluated and the stack address of the outermost one,
+    // calls fail once the stack grew past three quarters of `stack_size`
+    evaluation_depth: usize,
+    stack_base: usize,
+    stack_size: usize,
+    // Class the running method was found in and the object it was called on, for `super()`
+    method: Option<(Rc<Class>, Token)>,
+    // Top-level names of the files that are not running, `variables` holds those of `source`
+    globals: HashMap<String, HashMap<String, Token>>,
+    // Imported modules by canonical path or `<name>` for built-in modules, and the
+    // files whose import has not finished
+    modules: HashMap<PathBuf, Rc<Module>>,
+    importing: Vec<PathBuf>,
+    random: Random,
+    clock: Box<dyn Clock>,
+    files: Box<dyn FileSystem>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Self {
         Interpreter {
             variables: HashMap::new(),
+            locals: None,
             line_number: 0,
             pass_lock: false,
             indent_level: 0,
             new_indent_level: false,
+            selected_test: None,
+            matches: Vec::new(),
+            tries: Vec::new(),
+            recording: None,
+            classes: Vec::new(),
+            withs: Vec::new(),
+            returning: None,
+            source: Source {
+                file: String::from("<input>"),
+                text: Rc::from(""),
+            },
+            function: String::from("<module>"),
+            depth: 0,
+            evaluation_depth: 0,
+            stack_base: 0,
+            stack_size: DEFAULT_STACK_SIZE,
+            method: None,
+            globals: HashMap::new(),
+            modules: HashMap::new(),
+            importing: Vec::new(),
+            random: Random::new(),
+            clock: Box::new(SystemClock::new()),
+            files: Box::new(SystemFileSystem),
+        }
+    }
+
+    pub fn select_test(&mut self, name: &str) {
+        // Only the body of the selected test block is executed
+        self.selected_test = Some(name.to_string());
+    }
+
+    // Name of the executed file shown in tracebacks
+    pub fn set_file(&mut self, file: &str) {
+        self.source.file = file.to_string();
+        // A module importing the program itself is a circular import
+        self.importing = self.files.canonicalize(file).map(PathBuf::from).into_iter().collect();
+    }
+
+    // Native stack of the thread the interpreter runs on, deeply nested code fails
+    // with a RecursionError before it runs out
+    pub fn set_stack_size(&mut self, bytes: usize) {
+        self.stack_size = bytes;
+    }
+
+    // Seed of the `random` module, the same seed gives the same numbers
+    pub fn set_seed(&mut self, seed: u64) {
+        self.random.seed(seed);
+    }
+
+    // Clock of the `time` module, a `VirtualClock` makes `sleep` return at once
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    // File system of `open`, the `files` module and `import`, embedders can restrict or
+    // replace it. Set it before `set_file`, which resolves the path of the program.
+    pub fn set_file_system(&mut self, files: Box<dyn FileSystem>) {
+        self.files = files;
+    }
+
+    pub(crate) fn set_source(&mut self, text: &str) {
+        self.source.text = Rc::from(text);
+    }
+
+    // Traceback frame of the statement starting on this line
+    pub(crate) fn frame(&self, line: usize) -> Frame {
+        let text = self.source.text.lines().nth(line.saturating_sub(1)).unwrap_or("");
+        let source = text.trim_start();
+        Frame {
+            function: self.function.clone(),
+            file: self.source.file.clone(),
+            line,
+            column: text[..text.len() - source.len()].chars().count() + 1,
+            source: source.trim_end().to_string(),
+            depth: self.depth,
+        }
+    }
+
+    pub fn interpret_line(&mut self, node: &ASTNode, line_number: usize) -> Result<Token, EzError> {
+        self.line_number = line_number;
+        let indent_level = node.indent_level();
+        // Lines indented below a `def` are its body
+        if let Some(recording) = &mut self.recording {
+            if indent_level > recording.indent_level {
+                recording.body.push((node.clone(), line_number));
+                return Ok(Token::None);
+            }
+            self.end_recording();
+        }
+        self.close_classes(indent_level);
+        self.close_withs(indent_level)?;
+        let arm = matches!(node, ASTNode::ExceptArm { .. } | ASTNode::FinallyArm { .. });
+        self.close_tries(indent_level, arm)?;
+        if self.returning.is_some() && !self.runs_while_returning(node) {
+            return Ok(Token::None);
+        }
+        match self.interpret_block_line(node) {
+            Ok(token) => Ok(token),
+            Err(error) => {
+                let error = error
+                    .with_line(line_number)
+                    .with_frame(self.frame(line_number));
+                self.catch(error, node.indent_level())?;
+                Ok(Token::None)
+            }
+        }
+    }
+
+    // Ends the program, an error still pending in a `try` is raised
+    pub fn finish(&mut self) -> Result<(), EzError> {
+        self.end_recording();
+        self.close_classes(0);
+        self.close_withs(0)?;
+        self.close_tries(0, false)
+    }
+
+    fn end_recording(&mut self) {
+        if let Some(recording) = self.recording.take() {
+            let function = Function {
+                name: recording.name.clone(),
+                parameters: recording.parameters,
+                body: recording.body,
+                source: self.source.clone(),
+            };
+            self.bind(recording.name, Token::Function(Rc::new(function)));
+        }
+    }
+
+    // Defines the classes whose body ends before a line at this indent level
+    fn close_classes(&mut self, indent_level: usize) {
+        while self
+            .classes
+            .last()
+            .is_some_and(|state| state.indent_level >= indent_level)
+        {
+            if let Some(state) = self.classes.pop() {
+                let class = Class {
+                    name: state.name.clone(),
+                    base: state.base,
+                    attributes: state.attributes,
+                    error: state.error,
+                };
+                self.bind(state.name, Token::Type(Rc::new(class)));
+            }
+        }
+    }
+
+    // After `return` only the `finally` arms of open try statements still run
+    fn runs_while_returning(&self, node: &ASTNode) -> bool {
+        match self.tries.last() {
+            Some(state) if matches!(node, ASTNode::FinallyArm { .. }) => {
+                state.indent_level == node.indent_level()
+            }
+            Some(state) => state.in_finally && node.indent_level() > state.indent_level,
+            None => false,
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<Token> {
+        if let Some(value) = self.classes.last().and_then(|state| state.attributes.get(name)) {
+            return Some(value.clone());
+        }
+        if let Some(value) = self.locals.as_ref().and_then(|locals| locals.get(name)) {
+            return Some(value.clone());
+        }
+        self.variables.get(name).cloned()
+    }
+
+    fn lookup_mut(&mut self, name: &str) -> Option<&mut Token> {
+        if let Some(state) = self.classes.last_mut() {
+            if state.attributes.contains_key(name) {
+                return state.attributes.get_mut(name);
+            }
+        }
+        if let Some(locals) = &mut self.locals {
+            if locals.contains_key(name) {
+                return locals.get_mut(name);
+            }
+        }
+        self.variables.get_mut(name)
+    }
+
+    // Binds in the running class body, else in the function call, else globally
+    fn bind(&mut self, name: String, value: Token) {
+        if let Some(state) = self.classes.last_mut() {
+            state.attributes.insert(name, value);
+        } else if let Some(locals) = &mut self.locals {
+            locals.insert(name, value);
+        } else {
+            self.variables.insert(name, value);
+        }
+    }
+
+    fn call(&mut self, callee: Token, arguments: Vec<Token>) -> Result<Token, EzError> {
+        match callee {
+            Token::Function(function) => self.call_function(&function, arguments, None),
+            Token::Type(class) => self.instantiate(&class, arguments),
+            Token::Native(native) => match native.module {
+                "math" => math::call(native.name, &arguments),
+                "random" => self.random.call(native.name, &arguments),
+                "time" => time::call(self.clock.as_mut(), native.name, &arguments),
+                "files" => files::call(self.files.as_mut(), native.name, &arguments),
+                _ => Err(EzError::new(
+                    "i002",
+                    format!("Function not found: {}", native.name),
+                )),
+            },
+            _ => Err(EzError::new(
+                "i003",
+                format!("Value is not callable: {}", display(&callee)?),
+            )),
+        }
+    }
+
+    // Runs the body with the arguments bound to the parameters, the caller's block
+    // state is put aside until the body ends or returns
+    fn call_function(
+        &mut self,
+        function: &Rc<Function>,
+        arguments: Vec<Token>,
+        method: Option<(Rc<Class>, Token)>,
+    ) -> Result<Token, EzError> {
+        if arguments.len() != function.parameters.len() {
+            // The object a method is called on is not counted
+            let receiver = usize::from(method.is_some());
+            return Err(argument_count(
+                &function.name,
+                &function.parameters.len().saturating_sub(receiver).to_string(),
+                &arguments[receiver.min(arguments.len())..],
+            ));
+        }
+        if self.depth >= MAX_CALL_DEPTH {
+            return Err(EzError::new(
+                "i021",
+                String::from("Maximum call depth exceeded"),
+            ));
+        }
+        let locals = function.parameters.iter().cloned().zip(arguments).collect();
+        let caller = self.enter(
+            Some(locals),
+            function.source.clone(),
+            function.name.clone(),
+            method,
+        );
+        // The body starts a new block at the indent of its first line
+        self.new_indent_level = true;
+        let mut result = Ok(());
+        for (statement, line_number) in &function.body {
+            if self.returning.is_some() && self.tries.is_empty() {
+                break;
+            }
+            if let Err(error) = self.interpret_line(statement, *line_number) {
+                result = Err(error);
+                break;
+            }
+        }
+        if result.is_ok() {
+            result = self.finish();
+        } else {
+            // An error leaving the body still closes its `with` values
+            while let Some(state) = self.withs.pop() {
+                let _ = self.exit_context(&state.value);
+            }
+        }
+        let value = self.returning.take().unwrap_or(Token::None);
+        self.leave(caller);
+        result.map(|_| value)
+    }
+
+    // Puts the running code aside to run a function body or a module
+    fn enter(
+        &mut self,
+        locals: Option<HashMap<String, Token>>,
+        source: Source,
+        function: String,
+        method: Option<(Rc<Class>, Token)>,
+    ) -> CallState {
+        self.switch_globals(&source.file);
+        self.depth += 1;
+        CallState {
+            locals: std::mem::replace(&mut self.locals, locals),
+            line_number: self.line_number,
+            pass_lock: std::mem::take(&mut self.pass_lock),
+            indent_level: std::mem::take(&mut self.indent_level),
+            new_indent_level: std::mem::take(&mut self.new_indent_level),
+            matches: std::mem::take(&mut self.matches),
+            tries: std::mem::take(&mut self.tries),
+            recording: self.recording.take(),
+            classes: std::mem::take(&mut self.classes),
+            withs: std::mem::take(&mut self.withs),
+            returning: self.returning.take(),
+            source: std::mem::replace(&mut self.source, source),
+            function: std::mem::replace(&mut self.function, function),
+            method: std::mem::replace(&mut self.method, method),
+        }
+    }
+
+    fn leave(&mut self, caller: CallState) {
+        self.depth -= 1;
+        self.switch_globals(&caller.source.file);
+        self.locals = caller.locals;
+        self.line_number = caller.line_number;
+        self.pass_lock = caller.pass_lock;
+        self.indent_level = caller.indent_level;
+        self.new_indent_level = caller.new_indent_level;
+        self.matches = caller.matches;
+        self.tries = caller.tries;
+        self.recording = caller.recording;
+        self.classes = caller.classes;
+        self.withs = caller.withs;
+        self.returning = caller.returning;
+        self.source = caller.source;
+        self.function = caller.function;
+        self.method = caller.method;
+    }
+
+    // Makes `variables` hold the top-level names of the file, functions see the
+    // names of the module they were defined in
+    fn switch_globals(&mut self, file: &str) {
+        if self.source.file == file {
+            return;
+        }
+        let globals = self.globals.remove(file).unwrap_or_default();
+        let previous = std::mem::replace(&mut self.variables, globals);
+        self.globals.insert(self.source.file.clone(), previous);
+    }
+
+    // Runs `name.ez` the first time it is imported, later imports share the module
+    fn import(&mut self, name: &str) -> Result<Rc<Module>, EzError> {
+        if let Some(globals) = builtin_module(name) {
+            let key = PathBuf::from(format!("<{}>", name));
+            if let Some(module) = self.modules.get(&key) {
+                return Ok(module.clone());
+            }
+            let module = Rc::new(Module {
+                name: name.to_string(),
+                file: key.display().to_string(),
+            });
+            self.globals.insert(module.file.clone(), globals);
+            self.modules.insert(key, module.clone());
+            return Ok(module);
+        }
+        let path = self.find_module(name)?;
+        let file = path.display().to_string();
+        let key = self.files.canonicalize(&file).map_or_else(|_| path.clone(), PathBuf::from);
+        if let Some(module) = self.modules.get(&key) {
+            return Ok(module.clone());
+        }
+        if self.importing.contains(&key) {
+            return Err(EzError::new(
+                "i022",
+                format!("Circular import of module {}", name),
+            ));
+        }
+        let text = self.files.read(&file).map_err(|error| {
+            EzError::new("i022", format!("Cannot read module {}: {}", name, error))
+        })?;
+        let source = Source {
+            file,
+            text: Rc::from(text.as_str()),
+        };
+        let module = Rc::new(Module {
+            name: name.to_string(),
+            file: source.file.clone(),
+        });
+        self.importing.push(key.clone());
+        let caller = self.enter(None, source, String::from("<module>"), None);
+        let result = crate::run_source(self, &text, false);
+        self.leave(caller);
+        self.importing.pop();
+        // A module that failed is loaded again by the next import
+        result?;
+        self.modules.insert(key, module.clone());
+        Ok(module)
+    }
+
+    // `name.ez` next to the running file, then in the directories listed in EZPATH
+    fn find_module(&mut self, name: &str) -> Result<PathBuf, EzError> {
+        let file_name = format!("{}.ez", name);
+        let here = Path::new(&self.source.file).parent().map(Path::to_path_buf);
+        let search_path = env::var_os("EZPATH");
+        let directories = here
+            .into_iter()
+            .chain(search_path.iter().flat_map(env::split_paths));
+        for directory in directories {
+            let path = directory.join(&file_name);
+            let file = path.display().to_string();
+            if self.files.exists(&file) && !self.files.is_dir(&file) {
+                return Ok(path);
+            }
+        }
+        Err(EzError::new("i022", format!("No module named {}", name)))
+    }
+
+    fn module_attribute(&self, module: &Module, name: &str) -> Option<Token> {
+        let globals = if module.file == self.source.file {
+            &self.variables
+        } else {
+            self.globals.get(&module.file)?
+        };
+        globals.get(name).cloned()
+    }
+
+    // Creates an object and runs `__init__` on it, error classes create error values
+    fn instantiate(&mut self, class: &Rc<Class>, arguments: Vec<Token>) -> Result<Token, EzError> {
+        if class.error.is_some() {
+            let message = match arguments.as_slice() {
+                [] => String::new(),
+                [message] => display(message)?,
+                _ => return Err(takes_no_arguments(&class.name, &arguments)),
+            };
+            return Ok(Token::Error(Box::new(EzError::of_class(class, message))));
+        }
+        let object = Object::new(class.clone());
+        match class.find("__init__") {
+            Some((owner, Token::Function(initializer))) => {
+                let mut arguments = arguments;
+                arguments.insert(0, Token::Object(object.clone()));
+                let receiver = Token::Object(object.clone());
+                self.call_function(&initializer, arguments, Some((owner, receiver)))?;
+            }
+            _ if !arguments.is_empty() => return Err(takes_no_arguments(&class.name, &arguments)),
+            _ => {}
+        }
+        Ok(Token::Object(object))
+    }
+
+    // Fields are looked up before the methods of the class and its bases
+    fn call_method(
+        &mut self,
+        object: Object,
+        method: &str,
+        arguments: Vec<Token>,
+    ) -> Result<Token, EzError> {
+        if let Some(value) = object.field(method) {
+            return self.call(value, arguments);
+        }
+        match object.class().find(method) {
+            Some((owner, Token::Function(function))) => {
+                let mut arguments = arguments;
+                arguments.insert(0, Token::Object(object.clone()));
+                self.call_function(&function, arguments, Some((owner, Token::Object(object))))
+            }
+            Some((_, value)) => self.call(value, arguments),
+            None => Err(no_attribute(&Token::Object(object), method)),
+        }
+    }
+
+    // `super().method(...)` calls the method of the base of the running method's class
+    fn call_super(&mut self, method: &str, arguments: Vec<Token>) -> Result<Token, EzError> {
+        let Some((class, receiver)) = self.method.clone() else {
+            return Err(EzError::new(
+                "i019",
+                String::from("super() outside of a method"),
+            ));
+        };
+        let found = class.base.as_ref().and_then(|base| base.find(method));
+        match found {
+            Some((owner, Token::Function(function))) => {
+                let mut arguments = arguments;
+                arguments.insert(0, receiver.clone());
+                self.call_function(&function, arguments, Some((owner, receiver)))
+            }
+            Some((_, value)) => self.call(value, arguments),
+            None => Err(EzError::new(
+                "i016",
+                format!("Base of {} has no attribute: {}", class.name, method),
+            )),
+        }
+    }
+
+    // Calls a protocol method like `__add__` if the object's class defines one.
+    // Fields are not consulted, protocols belong to the type.
+    fn call_protocol(
+        &mut self,
+        object: &Object,
+        method: &str,
+        arguments: Vec<Token>,
+    ) -> Result<Option<Token>, EzError> {
+        match object.class().find(method) {
+            Some((owner, Token::Function(function))) => {
+                let receiver = Token::Object(object.clone());
+                let mut arguments = arguments;
+                arguments.insert(0, receiver.clone());
+                self.call_function(&function, arguments, Some((owner, receiver))).map(Some)
+            }
+            Some((_, value)) => self.call(value, arguments).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // Operators on objects try the method of the left operand, then the reflected
+    // method of the right one. `None` lets the built-in operators handle the values.
+    fn operate_protocol(
+        &mut self,
+        left_val: &Token,
+        operator: &Token,
+        right_val: &Token,
+    ) -> Result<Option<Token>, EzError> {
+        let Some((method, reflected)) = protocol_methods(operator) else {
+            return Ok(None);
+        };
+        if let Token::Object(object) = left_val {
+            if let Some(result) = self.call_protocol(object, method, vec![right_val.clone()])? {
+                return Ok(Some(result));
+            }
+        }
+        if let Token::Object(object) = right_val {
+            if let Some(result) = self.call_protocol(object, reflected, vec![left_val.clone()])? {
+                return Ok(Some(result));
+            }
+        }
+        // Without `__ne__`, `!=` is the opposite of `__eq__`
+        if *operator == Token::Comparison(Compare::NotEqual) {
+            let equal = Token::Comparison(Compare::Equal);
+            if let Some(result) = self.operate_protocol(left_val, &equal, right_val)? {
+                return Ok(Some(Token::Boolean(!self.truthy(&result)?)));
+            }
+        }
+        Ok(None)
+    }
+
+    // Objects are true unless `__bool__` says otherwise or `__len__` is zero
+    fn truthy(&mut self, token: &Token) -> Result<bool, EzError> {
+        if let Token::Object(object) = token {
+            if let Some(value) = self.call_protocol(object, "__bool__", Vec::new())? {
+                return match value {
+                    Token::Boolean(value) => Ok(value),
+                    _ => Err(EzError::new(
+                        "i003",
+                        format!("__bool__ must return a boolean, got {}", type_name(&value)),
+                    )),
+                };
+            }
+            if let Some(length) = self.length(object)? {
+                return Ok(length != 0);
+            }
+        }
+        Ok(is_truthy(token))
+    }
+
+    fn length(&mut self, object: &Object) -> Result<Option<i64>, EzError> {
+        match self.call_protocol(object, "__len__", Vec::new())? {
+            Some(Token::Integer(length)) if length >= 0 => Ok(Some(length)),
+            Some(value) => Err(EzError::new(
+                "i003",
+                format!(
+                    "__len__ must return a non-negative integer, got {}",
+                    match value {
+                        Token::Integer(length) => length.to_string(),
+                        _ => type_name(&value).to_string(),
+                    }
+                ),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    // Text of a value for `print`, f-strings and `str`. Objects use `__str__`,
+    // then `__repr__`, and values inside lists use `__repr__`.
+    fn stringify(&mut self, token: &Token) -> Result<String, EzError> {
+        match token {
+            Token::Object(object) => match self.call_protocol(object, "__str__", Vec::new())? {
+                Some(text) => protocol_text("__str__", text),
+                None => self.represent(token),
+            },
+            _ => self.represent(token),
+        }
+    }
+
+    fn represent(&mut self, token: &Token) -> Result<String, EzError> {
+        match token {
+            Token::Object(object) => match self.call_protocol(object, "__repr__", Vec::new())? {
+                Some(text) => protocol_text("__repr__", text),
+                None => display(token),
+            },
+            Token::List(values) if values.iter().any(holds_object) => {
+                let mut parts = Vec::with_capacity(values.len());
+                for value in values {
+                    parts.push(match value {
+                        Token::String(text) => format!("{:?}", text),
+                        _ => self.represent(value)?,
+                    });
+                }
+                Ok(format!("[{}]", parts.join(", ")))
+            }
+            _ => display(token),
+        }
+    }
+
+    // Values of an object for unpacking and `in`. `__iter__` returns a list, a
+    // string or an iterator object whose `__next__` raises StopIteration at the end.
+    // An iterator is only advanced until `limit` values are taken.
+    fn iterate(&mut self, object: &Object, limit: Option<usize>) -> Result<Vec<Token>, EzError> {
+        let Some(iterator) = self.call_protocol(object, "__iter__", Vec::new())? else {
+            return Err(EzError::new(
+                "i003",
+                format!("{} object is not iterable", object.class().name),
+            ));
+        };
+        let iterator = match iterator {
+            Token::Object(iterator) => iterator,
+            Token::List(values) => return Ok(values),
+            Token::String(text) => {
+                return Ok(text.chars().map(|c| Token::String(c.to_string())).collect())
+            }
+            value => {
+                return Err(EzError::new(
+                    "i003",
+                    format!(
+                        "__iter__ must return a list or an iterator, got {}",
+                        type_name(&value)
+                    ),
+                ))
+            }
+        };
+        let mut values = Vec::new();
+        while limit != Some(values.len()) {
+            match self.call_protocol(&iterator, "__next__", Vec::new()) {
+                Ok(Some(value)) => values.push(value),
+                Ok(None) => {
+                    return Err(EzError::new(
+                        "i003",
+                        format!("{} object is not an iterator", iterator.class().name),
+                    ))
+                }
+                Err(error) if error.is_kind("StopIteration") => return Ok(values),
+                Err(error) => return Err(error),
+            }
+            check_sequence_length(values.len())?;
+        }
+        Ok(values)
+    }
+
+    // Objects become the list of their values before unpacking into `count` targets,
+    // one value more than that is enough to tell there are too many
+    fn iterable(&mut self, value: Token, count: usize) -> Result<Token, EzError> {
+        match value {
+            Token::Object(object) => self.iterate(&object, Some(count + 1)).map(Token::List),
+            value => Ok(value),
+        }
+    }
+
+    // `needle in haystack`, objects use `__contains__` or compare with their values
+    fn contains_value(&mut self, haystack: &Token, needle: &Token) -> Result<bool, EzError> {
+        let values = match (haystack, needle) {
+            (Token::Object(object), _) => {
+                if let Some(found) =
+                    self.call_protocol(object, "__contains__", vec![needle.clone()])?
+                {
+                    return self.truthy(&found);
+                }
+                self.iterate(object, None)?
+            }
+            (Token::List(values), Token::Object(_)) => values.clone(),
+            _ => return contains(haystack, needle),
+        };
+        let equal = Token::Comparison(Compare::Equal);
+        for value in values {
+            let result = self.operate(value, &equal, needle.clone())?;
+            if self.truthy(&result)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    // `object[index] = value`, nested indexes change the value `__getitem__` returns
+    fn set_object_item(
+        &mut self,
+        object: &Object,
+        indexes: &[Token],
+        value: Token,
+    ) -> Result<(), EzError> {
+        let Some((index, rest)) = indexes.split_first() else {
+            return Err(EzError::new("i015", String::from("Missing index")));
+        };
+        let value = if rest.is_empty() {
+            value
+        } else {
+            let Some(mut item) = self.call_protocol(object, "__getitem__", vec![index.clone()])?
+            else {
+                return Err(not_subscriptable(object));
+            };
+            match &item {
+                Token::Object(inner) => {
+                    let inner = inner.clone();
+                    return self.set_object_item(&inner, rest, value);
+                }
+                _ => set_item(&mut item, rest, value)?,
+            }
+            item
+        };
+        match self.call_protocol(object, "__setitem__", vec![index.clone(), value])? {
+            Some(_) => Ok(()),
+            None => Err(EzError::new(
+                "i003",
+                format!("{} object does not support item assignment", object.class().name),
+            )),
+        }
+    }
+
+    // Whether an `except` arm naming these types catches the error. The names must be
+    // built-in error types or error classes, a misspelled name is not silently skipped.
+    fn catches(&self, error: &EzError, kinds: &[String]) -> Result<bool, EzError> {
+        let mut caught = kinds.is_empty();
+        for kind in kinds {
+            caught |= match self.lookup(kind) {
+                Some(Token::Type(class)) if class.error.is_some() => error.is_instance(&class),
+                Some(value) => {
+                    return Err(EzError::new(
+                        "i003",
+                        format!("except needs an error type, {} is a {}", kind, type_name(&value)),
+                    ))
+                }
+                None if is_error_type(kind) => error.is_kind(kind),
+                None => {
+                    return Err(EzError::new(
+                        "i002",
+                        format!("Error type not found: {}", kind),
+                    ))
+                }
+            };
+        }
+        Ok(caught)
+    }
+
+    // Closes the values of the `with` statements the line at this indent level is no
+    // longer part of, an error in `__exit__` is raised at the `with`
+    fn close_withs(&mut self, indent_level: usize) -> Result<(), EzError> {
+        while self
+            .withs
+            .last()
+            .is_some_and(|state| state.indent_level >= indent_level)
+        {
+            if let Some(state) = self.withs.pop() {
+                if let Err(error) = self.exit_context(&state.value) {
+                    let error = error
+                        .with_line(state.line_number)
+                        .with_frame(self.frame(state.line_number));
+                    self.catch(error, state.indent_level)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Value bound by `with`. Files are bound as they are, objects need an `__exit__`
+    // method and `__enter__` may return another value.
+    fn enter_context(&mut self, value: &Token) -> Result<Token, EzError> {
+        match value {
+            Token::File(_) => Ok(value.clone()),
+            Token::Object(object) if object.class().find("__exit__").is_some() => Ok(self
+                .call_protocol(object, "__enter__", Vec::new())?
+                .unwrap_or_else(|| value.clone())),
+            Token::Object(object) => Err(EzError::new(
+                "i003",
+                format!("{} object does not support with, it has no __exit__", object.class().name),
+            )),
+            _ => Err(EzError::new(
+                "i003",
+                format!("{} value does not support with", type_name(value)),
+            )),
+        }
+    }
+
+    fn exit_context(&mut self, value: &Token) -> Result<(), EzError> {
+        match value {
+            Token::File(file) => {
+                file.close();
+                Ok(())
+            }
+            Token::Object(object) => self
+                .call_protocol(object, "__exit__", Vec::new())
+                .map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+
+    // Closes every `try` statement the line at this indent level is no longer part of
+    fn close_tries(&mut self, indent_level: usize, arm: bool) -> Result<(), EzError> {
+        while let Some(state) = self.tries.last() {
+            if state.indent_level < indent_level || (state.indent_level == indent_level && arm) {
+                break;
+            }
+            if let Some(state) = self.tries.pop() {
+                // A `return` in `finally` discards the pending error
+                if let (Some(error), None) = (state.error, &self.returning) {
+                    self.catch(error, state.indent_level)?;
+                }
+            }
         }
+        Ok(())
     }
 
-    pub fn interpret_line(&mut self, node: &ASTNode) -> Token {
-        self.line_number += 1;
+    // Hands an error raised on a line at this indent level to the innermost `try` around
+    // it, the rest of its block is skipped. Without one the error ends the program.
+    fn catch(&mut self, error: EzError, mut indent_level: usize) -> Result<(), EzError> {
+        loop {
+            let Some(index) = self
+                .tries
+                .iter()
+                .rposition(|state| state.indent_level < indent_level)
+            else {
+                return Err(error);
+            };
+            self.tries.truncate(index + 1);
+            if !self.tries[index].in_finally {
+                break;
+            }
+            // An error in `finally` replaces the pending one
+            if let Some(state) = self.tries.pop() {
+                indent_level = state.indent_level;
+            }
+        }
+        let Some(state) = self.tries.last_mut() else {
+            return Err(error);
+        };
+        let try_indent_level = state.indent_level;
+        // Errors raised by an `except` arm are not caught by the arms that follow it
+        state.arms_closed = state.handling.is_some();
+        state.handling = None;
+        state.error = Some(error);
+        self.matches.retain(|state| state.indent_level <= try_indent_level);
+        self.classes.retain(|state| state.indent_level < try_indent_level);
+        self.indent_level = try_indent_level;
+        self.pass_lock = true;
+        self.new_indent_level = false;
+        Ok(())
+    }
+
+    fn interpret_block_line(&mut self, node: &ASTNode) -> Result<Token, EzError> {
         let local_indent_level = node.indent_level();
 
         if self.new_indent_level {
@@ -33,7 +982,7 @@ impl Interpreter {
         } else if self.pass_lock {
             // Check if the interpreter is in a pass state
             if local_indent_level > self.indent_level {
-                return Token::None;
+                return Ok(Token::None);
             } else if local_indent_level == self.indent_level {
                 match node {
                     ASTNode::AlternativeOperation {
@@ -42,328 +991,1883 @@ impl Interpreter {
                     } => {
                         if let Some(elif_condition) = condition {
                             //Elif
-                            if self.interpret(elif_condition) == Token::Boolean(true) {
+                            let condition = self.interpret(elif_condition)?;
+                            if self.truthy(&condition)? {
                                 self.pass_lock = false;
                                 self.new_indent_level = true;
-                                return Token::Boolean(true);
+                                return Ok(Token::Boolean(true));
                             } else {
                                 self.pass_lock = true;
-                                return Token::Boolean(false);
+                                return Ok(Token::Boolean(false));
                             }
                         } else {
                             //Else
                             self.pass_lock = false;
                             self.new_indent_level = true;
-                            return Token::Boolean(true);
+                            return Ok(Token::Boolean(true));
                         }
                     }
                     _ => {
                         self.pass_lock = false;
                     }
                 }
+            } else {
+                // A dedent ends the skipped block
+                self.pass_lock = false;
             }
         } else if local_indent_level > self.indent_level {
             // Off limits
-            panic!("Error i010: Unexpected indent level on line {}", self.line_number);
+            return Err(EzError::new(
+                "i010",
+                String::from("Unexpected indent level"),
+            ));
         }
         self.interpret(node)
     }
 
-    fn print_interpret(&mut self, node: &ASTNode) -> String {
-        let token = self.interpret(node);
-        match token {
-            Token::Integer(value) => value.to_string(),
-            Token::Float(value) => value.to_string(),
-            Token::Boolean(value) => value.to_string(),
-            Token::String(value) => value,
-            _ => panic!("Error i009: Unexpected token: {:?} on line {}", token, self.line_number),
+    fn print_interpret(&mut self, node: &ASTNode) -> Result<String, EzError> {
+        let token = self.interpret(node)?;
+        self.stringify(&token)
+    }
+
+    fn interpret(&mut self, node: &ASTNode) -> Result<Token, EzError> {
+        let marker = 0u8;
+        let position = std::ptr::addr_of!(marker) as usize;
+        if self.evaluation_depth == 0 {
+            self.stack_base = position;
+        } else if self.stack_base.abs_diff(position) > self.stack_size / 4 * 3 {
+            return Err(EzError::new(
+                "i021",
+                String::from("Maximum recursion depth exceeded"),
+            ));
         }
+        self.evaluation_depth += 1;
+        let result = self.evaluate(node);
+        self.evaluation_depth -= 1;
+        result
     }
 
-    fn interpret(&mut self, node: &ASTNode) -> Token {
+    fn evaluate(&mut self, node: &ASTNode) -> Result<Token, EzError> {
         match node {
-            ASTNode::Integer(value, _indent_level) => Token::Integer(*value),
-            ASTNode::Float(value, _indent_level) => Token::Float(*value),
-            ASTNode::Boolean(value, _indent_level) => Token::Boolean(*value),
-            ASTNode::Identifier(name, _indent_level) => match self.variables.get(name) {
-                Some(token) => match token {
-                    Token::Integer(value) => Token::Integer(value.clone()),
-                    Token::Float(value) => Token::Float(value.clone()),
-                    Token::Boolean(value) => Token::Boolean(value.clone()),
-                    Token::String(value) => Token::String(value.clone()),
-                    _ => panic!("Error i001: Unexpected token: {:?} on line {}", token, self.line_number),
-                },
-                None => panic!("Error i002: Variable not found: {} on line {}", name, self.line_number),
+            ASTNode::Integer(value, _indent_level) => Ok(Token::Integer(*value)),
+            ASTNode::BigInteger(value, _indent_level) => Ok(Token::BigInteger(value.clone())),
+            ASTNode::Float(value, _indent_level) => Ok(Token::Float(*value)),
+            ASTNode::Boolean(value, _indent_level) => Ok(Token::Boolean(*value)),
+            ASTNode::Identifier(name, _indent_level) => match self.lookup(name) {
+                Some(token) => Ok(token),
+                None => Err(EzError::new("i002", format!("Variable not found: {}", name))),
             },
-            ASTNode::String(value, _indent_level) => Token::String(value.clone()),
-            ASTNode::OutputOperation {
+            ASTNode::String(value, _indent_level) => Ok(Token::String(value.clone())),
+            ASTNode::FormatString(segments, _indent_level) => {
+                let mut text = String::new();
+                for segment in segments {
+                    match segment {
+                        FormatSegment::Text(part) => text.push_str(part),
+                        FormatSegment::Value { value, spec } => {
+                            let mut value = self.interpret(value)?;
+                            if holds_object(&value) {
+                                value = Token::String(self.stringify(&value)?);
+                            }
+                            text.push_str(&strings::format_value(&value, spec)?);
+                        }
+                    }
+                }
+                check_sequence_length(text.len())?;
+                Ok(Token::String(text))
+            }
+            ASTNode::List(elements, _indent_level) => {
+                let mut values = Vec::with_capacity(elements.len());
+                for element in elements {
+                    values.push(self.interpret(element)?);
+                }
+                Ok(Token::List(values))
+            }
+            ASTNode::IndexOperation {
                 value,
+                index,
                 indent_level: _,
             } => {
-                let token_value = self.print_interpret(value);
-                println!("{}", token_value);
-                Token::None
+                let value = self.interpret(value)?;
+                let index = self.interpret(index)?;
+                match value {
+                    Token::Object(object) => self
+                        .call_protocol(&object, "__getitem__", vec![index])?
+                        .ok_or_else(|| not_subscriptable(&object)),
+                    value => index_value(value, index),
+                }
             }
-            ASTNode::BinaryOperation {
-                left,
-                operator,
-                right,
+            ASTNode::SliceOperation {
+                value,
+                start,
+                end,
                 indent_level: _,
             } => {
-                let left_val = self.interpret(left);
-                let right_val = self.interpret(right);
-                match operator.clone() {
-                    Token::Plus => match (left_val.clone(), right_val.clone()) {
-                        (Token::Integer(left_num), Token::Integer(right_num)) => {
-                            Token::Integer(left_num + right_num)
-                        }
-                        (Token::Float(left_num), Token::Float(right_num)) => {
-                            Token::Float(left_num + right_num)
-                        }
-                        (Token::Integer(left_num), Token::Float(right_num)) => {
-                            Token::Float(left_num as f64 + right_num)
-                        }
-                        (Token::Float(left_num), Token::Integer(right_num)) => {
-                            Token::Float(left_num + right_num as f64)
-                        }
-                        (Token::Boolean(left_bool), Token::Boolean(right_bool)) => {
-                            Token::Boolean(left_bool || right_bool)
-                        }
-                        (Token::Integer(_left_num), Token::Boolean(right_bool)) => {
-                            Token::Boolean(self.num_to_bool(left_val.clone()) || right_bool)
-                        }
-                        (Token::Boolean(left_bool), Token::Integer(_right_num)) => {
-                            Token::Boolean(left_bool || self.num_to_bool(right_val.clone()))
-                        }
-                        (Token::String(left_str), Token::String(right_str)) => {
-                            Token::String(format!("{}{}", left_str, right_str))
-                        }
-                        (Token::String(left_str), Token::Integer(right_num)) => {
-                            Token::String(format!("{}{}", left_str, right_num))
-                        }
-                        _ => panic!(
-                            "Error i003: Unexpected values {:?}, {:?}, {:?}, on line {}",
-                            left_val, operator, right_val, self.line_number
-                        ),
+                let value = self.interpret(value)?;
+                let start = match start {
+                    Some(start) => Some(self.interpret(start)?),
+                    None => None,
+                };
+                let end = match end {
+                    Some(end) => Some(self.interpret(end)?),
+                    None => None,
+                };
+                slice_value(value, start, end)
+            }
+            ASTNode::AttributeAccess {
+                object,
+                name,
+                indent_level: _,
+            } => {
+                let object = self.interpret(object)?;
+                match (&object, name.as_str()) {
+                    (Token::Error(error), "message") => Ok(Token::String(error.message.clone())),
+                    (Token::Error(error), "code") => Ok(Token::String(error.code.to_string())),
+                    (Token::Error(error), "kind") => Ok(Token::String(error.kind.clone())),
+                    (Token::Error(error), "line") => Ok(Token::Integer(error.line as i64)),
+                    // Attributes and methods of the class of a user-defined error type
+                    (Token::Error(error), _) => error
+                        .class
+                        .as_ref()
+                        .and_then(|class| class.find(name))
+                        .map(|(_, value)| value)
+                        .ok_or_else(|| no_attribute(&object, name)),
+                    (Token::Object(instance), _) => match instance.field(name) {
+                        Some(value) => Ok(value),
+                        None => match instance.class().find(name) {
+                            Some((_, value)) => Ok(value),
+                            None => Err(no_attribute(&object, name)),
+                        },
                     },
-                    Token::Minus => match (left_val.clone(), right_val.clone()) {
-                        (Token::Integer(left_num), Token::Integer(right_num)) => {
-                            Token::Integer(left_num - right_num)
-                        }
-                        (Token::Boolean(left_bool), Token::Boolean(right_bool)) => {
-                            Token::Boolean(left_bool && !right_bool)
-                        }
-                        (Token::Boolean(left_bool), Token::Integer(_right_num)) => {
-                            Token::Boolean(left_bool && !self.num_to_bool(right_val.clone()))
-                        }
-                        (Token::Integer(_left_num), Token::Boolean(right_bool)) => {
-                            Token::Boolean(self.num_to_bool(left_val.clone()) && !right_bool)
-                        }
-                        _ => panic!(
-                            "Error i003: Unexpected values {:?}, {:?}, {:?}, on line {}",
-                            left_val, operator, right_val, self.line_number
-                        ),
+                    (Token::Type(class), _) => match class.find(name) {
+                        Some((_, value)) => Ok(value),
+                        None => Err(no_attribute(&object, name)),
                     },
-                    Token::Asterisk => match (left_val.clone(), right_val.clone()) {
-                        (Token::Integer(left_num), Token::Integer(right_num)) => {
-                            Token::Integer(left_num * right_num)
-                        }
-                        (Token::Boolean(left_bool), Token::Boolean(right_bool)) => {
-                            Token::Boolean(left_bool && right_bool)
-                        }
-                        _ => panic!(
-                            "Error i003: Unexpected values {:?}, {:?}, {:?}, on line {}",
-                            left_val, operator, right_val, self.line_number
-                        ),
-                    },
-                    Token::Slash => {
-                        if let (Token::Integer(left_num), Token::Integer(right_num)) =
-                            (left_val.clone(), right_val.clone())
-                        {
-                            Token::Integer(left_num / right_num)
-                        } else {
-                            panic!(
-                                "Error i003: Unexpected values {:?}, {:?}, {:?}, on line {}",
-                                left_val, operator, right_val, self.line_number
-                            );
-                        }
+                    (Token::Module(module), _) => self
+                        .module_attribute(module, name)
+                        .ok_or_else(|| no_attribute(&object, name)),
+                    (Token::DateTime(value), _) => {
+                        time::attribute(value, name).ok_or_else(|| no_attribute(&object, name))
                     }
-                    Token::Modulo => {
-                        if let (Token::Integer(left_num), Token::Integer(right_num)) =
-                            (left_val.clone(), right_val.clone())
-                        {
-                            Token::Integer(left_num % right_num)
-                        } else {
-                            panic!(
-                                "Error i003: Unexpected values {:?}, {:?}, {:?}, on line {}",
-                                left_val, operator, right_val, self.line_number
-                            );
-                        }
+                    (Token::File(file), _) => {
+                        files::attribute(file, name).ok_or_else(|| no_attribute(&object, name))
                     }
-                    Token::And => match (left_val.clone(), right_val.clone()) {
-                        (Token::Boolean(left), Token::Boolean(right)) => {
-                            Token::Boolean(left && right)
-                        }
-                        (Token::Integer(_left), Token::Integer(_right)) => {
-                            Token::Boolean(self.num_to_bool(left_val.clone()) && self.num_to_bool(right_val.clone()))
-                        }
-                        (Token::Integer(_left), Token::Boolean(right)) => {
-                            Token::Boolean(self.num_to_bool(left_val.clone()) && right)
-                        }
-                        (Token::Boolean(left), Token::Integer(_right)) => {
-                            Token::Boolean(left && self.num_to_bool(right_val.clone()))
+                    _ => Err(no_attribute(&object, name)),
+                }
+            }
+            ASTNode::MethodCall {
+                object,
+                method,
+                arguments,
+                indent_level: _,
+            } => {
+                if let ASTNode::FunctionCall {
+                    name,
+                    arguments: super_arguments,
+                    ..
+                } = object.as_ref()
+                {
+                    if name == "super" && super_arguments.is_empty() && self.lookup(name).is_none() {
+                        let arguments = self.interpret_arguments(arguments)?;
+                        return self.call_super(method, arguments);
+                    }
+                }
+                let object = self.interpret(object)?;
+                let arguments = self.interpret_arguments(arguments)?;
+                match object {
+                    Token::String(value) => strings::call_method(&value, method, arguments),
+                    Token::DateTime(value) => time::call_method(&value, method, arguments),
+                    Token::File(file) => {
+                        files::call_method(self.files.as_mut(), &file, method, arguments)
+                    }
+                    Token::Object(instance) => self.call_method(instance, method, arguments),
+                    Token::Error(ref error) => {
+                        match error.class.as_ref().and_then(|class| class.find(method)) {
+                            Some((owner, Token::Function(function))) => {
+                                let mut arguments = arguments;
+                                arguments.insert(0, object.clone());
+                                self.call_function(&function, arguments, Some((owner, object)))
+                            }
+                            Some((_, callee)) => self.call(callee, arguments),
+                            None => Err(no_attribute(&object, method)),
                         }
-                        _ => panic!("Error i003: Unexpected values on line {}", self.line_number),
+                    }
+                    Token::Type(ref class) => match class.find(method) {
+                        Some((_, callee)) => self.call(callee, arguments),
+                        None => Err(no_attribute(&object, method)),
                     },
-                    Token::Or => match (left_val.clone(), right_val.clone()) {
-                        (Token::Boolean(left), Token::Boolean(right)) => {
-                            Token::Boolean(left || right)
-                        }
-                        (Token::Integer(_left), Token::Integer(_right)) => {
-                            Token::Boolean(self.num_to_bool(left_val.clone()) || self.num_to_bool(right_val.clone()))
-                        }
-                        (Token::Integer(_left), Token::Boolean(right)) => {
-                            Token::Boolean(self.num_to_bool(left_val.clone()) || right)
-                        }
-                        (Token::Boolean(left), Token::Integer(_right)) => {
-                            Token::Boolean(left || self.num_to_bool(right_val.clone()))
-                        }
-                        _ => panic!("Error i003: Unexpected values on line {}", self.line_number),
+                    Token::Module(ref module) => match self.module_attribute(module, method) {
+                        Some(callee) => self.call(callee, arguments),
+                        None => Err(no_attribute(&object, method)),
                     },
-                    Token::Comparison(operator) => match operator {
-                        Compare::Equal => Token::Boolean(left_val == right_val),
-                        Compare::NotEqual => Token::Boolean(left_val != right_val),
-                        Compare::LessThan => match (left_val.clone(), right_val.clone()) {
-                            (Token::Integer(left), Token::Integer(right)) => {
-                                Token::Boolean(left < right)
-                            }
-                            _ => panic!("Error i003: Unexpected values on line {}", self.line_number),
-                        },
-                        Compare::LessThanOrEqual => match (left_val.clone(), right_val.clone()) {
-                            (Token::Integer(left), Token::Integer(right)) => {
-                                Token::Boolean(left <= right)
-                            }
-                            _ => panic!("Error i003: Unexpected values on line {}", self.line_number),
-                        },
-                        Compare::GreaterThan => match (left_val.clone(), right_val.clone()) {
-                            (Token::Integer(left), Token::Integer(right)) => {
-                                Token::Boolean(left > right)
-                            }
-                            _ => panic!("Error i003: Unexpected values on line {}", self.line_number),
-                        },
-                        Compare::GreaterThanOrEqual => {
-                            match (left_val.clone(), right_val.clone()) {
-                                (Token::Integer(left), Token::Integer(right)) => {
-                                    Token::Boolean(left >= right)
-                                }
-                                _ => panic!("Error i003: Unexpected values on line {}", self.line_number),
-                            }
-                        }
+                    _ => Err(EzError::new(
+                        "i016",
+                        format!("{} value has no method: {}", type_name(&object), method),
+                    )),
+                }
+            }
+            ASTNode::FunctionCall {
+                name,
+                arguments,
+                indent_level: _,
+            } => {
+                let arguments = self.interpret_arguments(arguments)?;
+                // Definitions of the program come before the built-in functions
+                if let Some(callee) = self.lookup(name) {
+                    return self.call(callee, arguments);
+                }
+                match (name.as_str(), arguments.as_slice()) {
+                    ("len", [Token::String(value)]) => Ok(Token::Integer(value.chars().count() as i64)),
+                    ("len", [Token::List(values)]) => Ok(Token::Integer(values.len() as i64)),
+                    ("len", [Token::Object(object)]) => match self.length(object)? {
+                        Some(length) => Ok(Token::Integer(length)),
+                        None => Err(EzError::new(
+                            "i003",
+                            format!("{} object has no len()", object.class().name),
+                        )),
                     },
-                    _ => panic!("Error i004: Unexpected operator: {:?} on line {}", operator, self.line_number),
+                    ("str", [value]) => Ok(Token::String(self.stringify(value)?)),
+                    ("open", _) => files::open(self.files.as_mut(), &arguments),
+                    ("len", [value]) => Err(EzError::new(
+                        "i003",
+                        format!("{} value has no len()", type_name(value)),
+                    )),
+                    ("len", _) => Err(EzError::new(
+                        "i017",
+                        format!("len takes 1 argument but {} were given", arguments.len()),
+                    )),
+                    (kind, []) if is_error_type(kind) => {
+                        Ok(Token::Error(Box::new(EzError::raised(kind, String::new()))))
+                    }
+                    (kind, [message]) if is_error_type(kind) => Ok(Token::Error(Box::new(
+                        EzError::raised(kind, display(message)?),
+                    ))),
+                    _ => Err(EzError::new("i002", format!("Function not found: {}", name))),
+                }
+            }
+            ASTNode::OutputOperation {
+                value,
+                indent_level: _,
+            } => {
+                let token_value = self.print_interpret(value)?;
+                println!("{}", token_value);
+                Ok(Token::None)
+            }
+            ASTNode::BinaryOperation {
+                left,
+                operator,
+                right,
+                indent_level: _,
+            } => {
+                let left_val = self.interpret(left)?;
+                let right_val = self.interpret(right)?;
+                self.operate(left_val, operator, right_val)
+            }
+            ASTNode::ComparisonChain {
+                operands,
+                operators,
+                indent_level: _,
+            } => {
+                // `a < b < c` is `a < b and b < c`, stopping at the first false comparison
+                let mut left_val = self.interpret(&operands[0])?;
+                for (operator, operand) in operators.iter().zip(&operands[1..]) {
+                    let right_val = self.interpret(operand)?;
+                    let result = self.operate(left_val, operator, right_val.clone())?;
+                    if !self.truthy(&result)? {
+                        return Ok(result);
+                    }
+                    left_val = right_val;
                 }
+                Ok(Token::Boolean(true))
             }
             ASTNode::BindingOperation {
-                variable,
+                targets,
+                operator,
                 value,
                 indent_level: _,
             } => {
-                let token_value = self.interpret(value);
-                let variable_name = match variable {
-                    Token::Identifier(name) => name,
-                    _ => panic!("Error i005: Unexpected token: {:?}, on line {}", variable, self.line_number),
+                let token_value = match operator {
+                    // Compound assignment reads the target first
+                    Some(operator) => {
+                        let current = self.interpret(&targets[0])?;
+                        let value = self.interpret(value)?;
+                        self.operate(current, operator, value)?
+                    }
+                    None => self.interpret(value)?,
                 };
-                self.variables
-                    .insert(variable_name.clone(), token_value.clone());
-                token_value
+                if let [target] = targets.as_slice() {
+                    self.assign(target, token_value.clone())?;
+                } else {
+                    let values = self.iterable(token_value.clone(), targets.len())?;
+                    let values = unpack(values, targets.len())?;
+                    for (target, value) in targets.iter().zip(values) {
+                        self.assign(target, value)?;
+                    }
+                }
+                Ok(token_value)
+            }
+            ASTNode::UnaryOperation {
+                operator,
+                value,
+                indent_level: _,
+            } => {
+                let value = self.interpret(value)?;
+                match (operator, value) {
+                    (Token::Minus, Token::Integer(number)) => Ok(match number.checked_neg() {
+                        Some(negated) => Token::Integer(negated),
+                        None => Token::BigInteger(BigInt::from_i64(number).neg()),
+                    }),
+                    (Token::Minus, Token::BigInteger(number)) => Ok(integer_token(number.neg())),
+                    (Token::Minus, Token::Float(number)) => Ok(Token::Float(-number)),
+                    (Token::Plus, value) if is_number(&value) => Ok(value),
+                    (Token::Tilde, Token::Integer(number)) => Ok(Token::Integer(!number)),
+                    (Token::Tilde, Token::BigInteger(number)) => Ok(integer_token(number.not())),
+                    (_, value) => Err(EzError::new(
+                        "i003",
+                        format!(
+                            "bad operand type for unary {}: {}",
+                            operator_symbol(operator),
+                            type_name(&value)
+                        ),
+                    )),
+                }
             }
             ASTNode::LogicalOperation {
                 left,
                 operator,
                 right,
                 indent_level: _,
-            } => Token::Boolean(match operator {
+            } => match operator {
+                // Short-circuit and return the operand that decided the result
                 Token::And => {
-                    let left_val = self.interpret(left);
-                    let right_val = self.interpret(right);
-                    match (left_val.clone(), right_val.clone()) {
-                        (Token::Boolean(left), Token::Boolean(right)) => left && right,
-                        (Token::Integer(_left), Token::Integer(_right)) => {
-                            self.num_to_bool(left_val.clone()) && self.num_to_bool(right_val.clone())
-                        }
-                        (Token::Integer(_left), Token::Boolean(right)) => {
-                            self.num_to_bool(left_val.clone()) && right
-                        }
-                        (Token::Boolean(left), Token::Integer(_right)) => {
-                            left && self.num_to_bool(right_val.clone())
-                        }
-                        _ => panic!("Error i006: Unexpected values on line {}", self.line_number),
+                    let left_val = self.interpret(left)?;
+                    if !self.truthy(&left_val)? {
+                        return Ok(left_val);
                     }
+                    self.interpret(right)
                 }
                 Token::Or => {
-                    let left_val = self.interpret(left);
-                    let right_val = self.interpret(right);
-                    match (left_val.clone(), right_val.clone()) {
-                        (Token::Boolean(left), Token::Boolean(right)) => left || right,
-                        (Token::Integer(_left), Token::Integer(_right)) => {
-                            self.num_to_bool(left_val.clone()) || self.num_to_bool(right_val.clone())
-                        }
-                        (Token::Integer(_left), Token::Boolean(right)) => {
-                            self.num_to_bool(left_val.clone()) || right
-                        }
-                        (Token::Boolean(left), Token::Integer(_right)) => {
-                            left || self.num_to_bool(right_val.clone())
-                        }
-                        _ => panic!("Error i006: Unexpected values on line {}", self.line_number),
+                    let left_val = self.interpret(left)?;
+                    if self.truthy(&left_val)? {
+                        return Ok(left_val);
                     }
+                    self.interpret(right)
                 }
                 Token::Not => {
-                    let right_val = self.interpret(right);
-                    match right_val {
-                        Token::Boolean(right) => !right,
-                        Token::Integer(_right) => !self.num_to_bool(right_val.clone()),
-                        _ => panic!("Error i006: Unexpected values on line {}", self.line_number),
-                    }
+                    let value = self.interpret(right)?;
+                    Ok(Token::Boolean(!self.truthy(&value)?))
                 }
-                _ => panic!("Error i007: Unexpected operator: {:?} on line {}", operator, self.line_number),
-            }),
+                _ => Err(EzError::new(
+                    "i007",
+                    format!("Unexpected operator: {}", operator_symbol(operator)),
+                )),
+            },
             ASTNode::ConditionalOperation {
                 condition,
                 indent_level,
             } => {
-                let condition_val = self.interpret(condition);
-                match condition_val {
-                    Token::Boolean(true) => {
-                        self.indent_level = indent_level.clone();
-                        self.pass_lock = false;
-                        self.new_indent_level = true;
-                        Token::Boolean(true)
+                let condition_val = self.interpret(condition)?;
+                self.indent_level = *indent_level;
+                if self.truthy(&condition_val)? {
+                    self.pass_lock = false;
+                    self.new_indent_level = true;
+                    Ok(Token::Boolean(true))
+                } else {
+                    self.pass_lock = true;
+                    self.new_indent_level = false;
+                    Ok(Token::Boolean(false))
+                }
+            }
+            ASTNode::ConditionalExpression {
+                condition,
+                value,
+                alternative,
+                indent_level: _,
+            } => {
+                let condition = self.interpret(condition)?;
+                if self.truthy(&condition)? {
+                    self.interpret(value)
+                } else {
+                    self.interpret(alternative)
+                }
+            }
+            ASTNode::MatchStatement {
+                subject,
+                indent_level,
+            } => {
+                let subject = self.interpret(subject)?;
+                self.matches.retain(|state| state.indent_level < *indent_level);
+                self.matches.push(MatchState {
+                    indent_level: *indent_level,
+                    subject,
+                    matched: false,
+                });
+                self.indent_level = *indent_level;
+                self.pass_lock = false;
+                self.new_indent_level = true;
+                Ok(Token::None)
+            }
+            ASTNode::CaseArm {
+                pattern,
+                guard,
+                indent_level,
+            } => {
+                // Belongs to the closest match statement above it
+                self.matches.retain(|state| state.indent_level < *indent_level);
+                let (subject, matched) = match self.matches.last() {
+                    Some(state) => (state.subject.clone(), state.matched),
+                    None => {
+                        return Err(EzError::new(
+                            "i019",
+                            String::from("case outside of match"),
+                        ))
                     }
-                    Token::Boolean(false) => {
-                        self.indent_level = indent_level.clone();
-                        self.pass_lock = true;
-                        self.new_indent_level = false;
-                        Token::Boolean(false)
+                };
+                self.indent_level = *indent_level;
+                let mut taken = false;
+                if !matched {
+                    let mut bindings = Vec::new();
+                    if self.match_pattern(pattern, &subject, &mut bindings)? {
+                        for (name, value) in bindings {
+                            self.bind(name, value);
+                        }
+                        taken = match guard {
+                            Some(guard) => {
+                                let guard = self.interpret(guard)?;
+                                self.truthy(&guard)?
+                            }
+                            None => true,
+                        };
                     }
-                    _ => panic!("Error i008: Unexpected value: {:?} on line {}", condition_val, self.line_number),
                 }
+                if let Some(state) = self.matches.last_mut() {
+                    state.matched |= taken;
+                }
+                self.pass_lock = !taken;
+                self.new_indent_level = taken;
+                Ok(Token::Boolean(taken))
             }
             ASTNode::AlternativeOperation {
                 condition: _,
                 indent_level,
             } => {
-                self.indent_level = indent_level.clone();
+                self.indent_level = *indent_level;
                 self.pass_lock = true;
-                Token::None
+                Ok(Token::None)
+            }
+            ASTNode::TryStatement { indent_level } => {
+                self.tries.retain(|state| state.indent_level < *indent_level);
+                self.tries.push(TryState {
+                    indent_level: *indent_level,
+                    error: None,
+                    handling: None,
+                    arms_closed: false,
+                    in_finally: false,
+                });
+                self.indent_level = *indent_level;
+                self.pass_lock = false;
+                self.new_indent_level = true;
+                Ok(Token::None)
+            }
+            ASTNode::ExceptArm {
+                kinds,
+                name,
+                indent_level,
+            } => {
+                let pending = match self.tries.last() {
+                    Some(state) if state.indent_level == *indent_level && !state.in_finally => {
+                        state.error.clone().filter(|_| !state.arms_closed)
+                    }
+                    _ => {
+                        return Err(EzError::new(
+                            "i019",
+                            String::from("except outside of try"),
+                        ))
+                    }
+                };
+                // The named types are only looked up when there is an error to catch
+                let caught = match pending {
+                    Some(error) => self.catches(&error, kinds)?,
+                    None => false,
+                };
+                let Some(state) = self.tries.last_mut() else {
+                    return Ok(Token::None);
+                };
+                state.handling = None;
+                self.indent_level = *indent_level;
+                if !caught {
+                    self.pass_lock = true;
+                    return Ok(Token::Boolean(false));
+                }
+                state.arms_closed = true;
+                state.handling = state.error.take();
+                if let (Some(name), Some(error)) = (name, &state.handling) {
+                    let error = Token::Error(Box::new(error.clone()));
+                    self.bind(name.clone(), error);
+                }
+                self.pass_lock = false;
+                self.new_indent_level = true;
+                Ok(Token::Boolean(true))
+            }
+            ASTNode::FinallyArm { indent_level } => {
+                match self.tries.last_mut() {
+                    Some(state) if state.indent_level == *indent_level && !state.in_finally => {
+                        state.handling = None;
+                        state.in_finally = true;
+                    }
+                    _ => {
+                        return Err(EzError::new(
+                            "i019",
+                            String::from("finally outside of try"),
+                        ))
+                    }
+                }
+                self.indent_level = *indent_level;
+                self.pass_lock = false;
+                self.new_indent_level = true;
+                Ok(Token::None)
+            }
+            ASTNode::RaiseStatement {
+                value,
+                indent_level,
+            } => match value {
+                Some(value) => match self.interpret(value)? {
+                    Token::Error(error) => Err(*error),
+                    value => Err(EzError::new(
+                        "i003",
+                        format!("Can only raise errors, found: {}", display(&value)?),
+                    )),
+                },
+                None => {
+                    // Re-raise the error of the `except` arm this statement is in
+                    let handling = self
+                        .tries
+                        .iter()
+                        .rev()
+                        .find(|state| state.indent_level < *indent_level)
+                        .and_then(|state| state.handling.clone());
+                    match handling {
+                        Some(error) => Err(error),
+                        None => Err(EzError::new(
+                            "i020",
+                            String::from("No error to re-raise"),
+                        )),
+                    }
+                }
+            },
+            ASTNode::FunctionDefinition {
+                name,
+                parameters,
+                indent_level,
+            } => {
+                self.recording = Some(Recording {
+                    indent_level: *indent_level,
+                    name: name.clone(),
+                    parameters: parameters.clone(),
+                    body: Vec::new(),
+                });
+                Ok(Token::None)
+            }
+            ASTNode::ClassDefinition {
+                name,
+                base,
+                indent_level,
+            } => {
+                let (base, error) = match base {
+                    None => (None, None),
+                    Some(base) => match self.lookup(base) {
+                        Some(Token::Type(class)) => {
+                            let error = class.error.clone();
+                            (Some(class), error)
+                        }
+                        Some(value) => {
+                            return Err(EzError::new(
+                                "i003",
+                                format!("Base class is not a class: {}", display(&value)?),
+                            ))
+                        }
+                        // Classes deriving from a built-in error type are error types
+                        None if is_error_type(base) => (None, Some(base.clone())),
+                        None => {
+                            return Err(EzError::new(
+                                "i002",
+                                format!("Class not found: {}", base),
+                            ))
+                        }
+                    },
+                };
+                self.classes.push(ClassState {
+                    indent_level: *indent_level,
+                    name: name.clone(),
+                    base,
+                    error,
+                    attributes: HashMap::new(),
+                });
+                self.indent_level = *indent_level;
+                self.pass_lock = false;
+                self.new_indent_level = true;
+                Ok(Token::None)
+            }
+            ASTNode::ReturnStatement {
+                value,
+                indent_level: _,
+            } => {
+                if self.locals.is_none() {
+                    return Err(EzError::new(
+                        "i019",
+                        String::from("return outside of function"),
+                    ));
+                }
+                let value = match value {
+                    Some(value) => self.interpret(value)?,
+                    None => Token::None,
+                };
+                self.returning = Some(value);
+                Ok(Token::None)
+            }
+            ASTNode::ImportStatement {
+                module,
+                alias,
+                indent_level: _,
+            } => {
+                let value = self.import(module)?;
+                self.bind(alias.clone().unwrap_or_else(|| module.clone()), Token::Module(value));
+                Ok(Token::None)
+            }
+            ASTNode::FromImport {
+                module,
+                names,
+                indent_level: _,
+            } => {
+                let value = self.import(module)?;
+                for (name, alias) in names {
+                    let Some(token) = self.module_attribute(&value, name) else {
+                        return Err(EzError::new(
+                            "i022",
+                            format!("Cannot import name {} from module {}", name, module),
+                        ));
+                    };
+                    self.bind(alias.clone(), token);
+                }
+                Ok(Token::None)
+            }
+            ASTNode::WithStatement {
+                value,
+                name,
+                indent_level,
+            } => {
+                let value = self.interpret(value)?;
+                let bound = self.enter_context(&value)?;
+                if let Some(name) = name {
+                    self.bind(name.clone(), bound);
+                }
+                self.withs.push(WithState {
+                    indent_level: *indent_level,
+                    line_number: self.line_number,
+                    value,
+                });
+                self.indent_level = *indent_level;
+                self.pass_lock = false;
+                self.new_indent_level = true;
+                Ok(Token::None)
+            }
+            ASTNode::TestDefinition { name, indent_level } => {
+                self.indent_level = *indent_level;
+                if self.selected_test.as_ref() == Some(name) {
+                    self.pass_lock = false;
+                    self.new_indent_level = true;
+                    Ok(Token::Boolean(true))
+                } else {
+                    self.pass_lock = true;
+                    self.new_indent_level = false;
+                    Ok(Token::Boolean(false))
+                }
+            }
+            ASTNode::AssertOperation {
+                condition,
+                message,
+                indent_level: _,
+            } => {
+                // Evaluate comparison operands separately to report their values
+                let (condition_val, values) = match condition.as_ref() {
+                    ASTNode::BinaryOperation {
+                        left,
+                        operator: operator @ Token::Comparison(_),
+                        right,
+                        indent_level: _,
+                    } => {
+                        let left_val = self.interpret(left)?;
+                        let right_val = self.interpret(right)?;
+                        let values = format!(
+                            " (left: {}, right: {})",
+                            self.stringify(&left_val)?,
+                            self.stringify(&right_val)?
+                        );
+                        (self.operate(left_val, operator, right_val)?, values)
+                    }
+                    _ => {
+                        let condition_val = self.interpret(condition)?;
+                        let values =
+                            format!(" (value: {})", self.stringify(&condition_val)?);
+                        (condition_val, values)
+                    }
+                };
+                if !self.truthy(&condition_val)? {
+                    let message = match message {
+                        Some(message) => self.print_interpret(message)?,
+                        None => String::from("assertion failed"),
+                    };
+                    return Err(EzError::new("i012", format!("{}{}", message, values)));
+                }
+                Ok(Token::None)
             }
         }
     }
 
-    fn num_to_bool(&self, token: Token) -> bool {
-        match token {
-            Token::Integer(num) => num != 0,
-            Token::Float(num) => num != 0.0,
-            _ => panic!("Error i011: Unexpected token: {:?} on line {}", token, self.line_number)
+    fn assign(&mut self, target: &ASTNode, value: Token) -> Result<(), EzError> {
+        match target {
+            ASTNode::Identifier(name, _indent_level) => {
+                self.bind(name.clone(), value);
+                Ok(())
+            }
+            // Nested destructuring, `[a, b], c = pairs`
+            ASTNode::List(targets, _indent_level) => {
+                let values = unpack(self.iterable(value, targets.len())?, targets.len())?;
+                for (target, value) in targets.iter().zip(values) {
+                    self.assign(target, value)?;
+                }
+                Ok(())
+            }
+            ASTNode::IndexOperation { .. } => {
+                // Walk down `xs[i][j]` to the variable holding the outermost list
+                let mut index_nodes = Vec::new();
+                let mut root = target;
+                while let ASTNode::IndexOperation { value, index, .. } = root {
+                    index_nodes.push(index.as_ref());
+                    root = value;
+                }
+                let mut indexes = Vec::with_capacity(index_nodes.len());
+                for index in index_nodes.into_iter().rev() {
+                    indexes.push(self.interpret(index)?);
+                }
+                let name = match root {
+                    ASTNode::Identifier(name, _indent_level) => name,
+                    // `self.items[0] = value` changes the list held by the field
+                    ASTNode::AttributeAccess { object, name, .. } => {
+                        let object = self.interpret(object)?;
+                        let Token::Object(instance) = &object else {
+                            return Err(no_attribute(&object, name));
+                        };
+                        let Some(mut container) = instance.field(name) else {
+                            return Err(no_attribute(&object, name));
+                        };
+                        if let Token::Object(container) = &container {
+                            return self.set_object_item(container, &indexes, value);
+                        }
+                        set_item(&mut container, &indexes, value)?;
+                        instance.set_field(name, container);
+                        return Ok(());
+                    }
+                    _ => return Err(cannot_assign(root)),
+                };
+                // Objects take `object[index] = value` through `__setitem__`
+                if let Some(Token::Object(object)) = self.lookup_mut(name) {
+                    let object = object.clone();
+                    return self.set_object_item(&object, &indexes, value);
+                }
+                match self.lookup_mut(name) {
+                    Some(container) => set_item(container, &indexes, value),
+                    None => Err(EzError::new("i002", format!("Variable not found: {}", name))),
+                }
+            }
+            ASTNode::AttributeAccess { object, name, .. } => match self.interpret(object)? {
+                Token::Object(instance) => {
+                    instance.set_field(name, value);
+                    Ok(())
+                }
+                Token::Type(class) => Err(EzError::new(
+                    "i016",
+                    format!("Class attributes cannot be changed: {}.{}", class.name, name),
+                )),
+                Token::Module(module) => Err(EzError::new(
+                    "i016",
+                    format!("Module attributes cannot be changed: {}.{}", module.name, name),
+                )),
+                Token::DateTime(_) => Err(EzError::new(
+                    "i016",
+                    format!("Date attributes cannot be changed: {}", name),
+                )),
+                Token::File(_) => Err(EzError::new(
+                    "i016",
+                    format!("File attributes cannot be changed: {}", name),
+                )),
+                object => Err(no_attribute(&object, name)),
+            },
+            _ => Err(cannot_assign(target)),
+        }
+    }
+
+    // Checks the value against the pattern and collects the names it binds
+    fn match_pattern(
+        &mut self,
+        pattern: &Pattern,
+        value: &Token,
+        bindings: &mut Vec<(String, Token)>,
+    ) -> Result<bool, EzError> {
+        match pattern {
+            Pattern::Wildcard => Ok(true),
+            Pattern::Capture(name) => {
+                bindings.push((name.clone(), value.clone()));
+                Ok(true)
+            }
+            Pattern::Literal(literal) => Ok(values_equal(&self.interpret(literal)?, value)),
+            Pattern::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let start = self.interpret(start)?;
+                let end = self.interpret(end)?;
+                // Values of another type are simply outside the range
+                let comparable = |bound: &Token| {
+                    (is_number(bound) && is_number(value))
+                        || matches!((bound, value), (Token::String(_), Token::String(_)))
+                };
+                if !comparable(&start) || !comparable(&end) {
+                    return Ok(false);
+                }
+                let upper = if *inclusive {
+                    Compare::LessThanOrEqual
+                } else {
+                    Compare::LessThan
+                };
+                let above_start = self.operate(
+                    start,
+                    &Token::Comparison(Compare::LessThanOrEqual),
+                    value.clone(),
+                )?;
+                let below_end = self.operate(value.clone(), &Token::Comparison(upper), end)?;
+                Ok(is_truthy(&above_start) && is_truthy(&below_end))
+            }
+            Pattern::List { elements, rest } => {
+                let Token::List(values) = value else {
+                    return Ok(false);
+                };
+                let (before, after) = match rest {
+                    Some((position, _)) => (*position, elements.len() - position),
+                    None => (elements.len(), 0),
+                };
+                if values.len() < before + after || (rest.is_none() && values.len() != before) {
+                    return Ok(false);
+                }
+                let tail = values.len() - after;
+                for (pattern, value) in elements[..before].iter().zip(&values[..before]) {
+                    if !self.match_pattern(pattern, value, bindings)? {
+                        return Ok(false);
+                    }
+                }
+                for (pattern, value) in elements[before..].iter().zip(&values[tail..]) {
+                    if !self.match_pattern(pattern, value, bindings)? {
+                        return Ok(false);
+                    }
+                }
+                if let Some((_, Some(name))) = rest {
+                    bindings.push((name.clone(), Token::List(values[before..tail].to_vec())));
+                }
+                Ok(true)
+            }
+            // Objects are dictionaries when they define `__contains__` and `__getitem__`
+            Pattern::Dictionary(entries) => {
+                let Token::Object(object) = value else {
+                    return Ok(false);
+                };
+                let class = object.class();
+                if class.find("__contains__").is_none() || class.find("__getitem__").is_none() {
+                    return Ok(false);
+                }
+                for (key, pattern) in entries {
+                    let key = self.interpret(key)?;
+                    if !self.contains_value(value, &key)? {
+                        return Ok(false);
+                    }
+                    let item = self
+                        .call_protocol(object, "__getitem__", vec![key])?
+                        .unwrap_or(Token::None);
+                    if !self.match_pattern(pattern, &item, bindings)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            Pattern::Alternatives(alternatives) => {
+                for alternative in alternatives {
+                    let mut alternative_bindings = Vec::new();
+                    if self.match_pattern(alternative, value, &mut alternative_bindings)? {
+                        bindings.extend(alternative_bindings);
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+        }
+    }
+
+    fn interpret_arguments(&mut self, arguments: &[ASTNode]) -> Result<Vec<Token>, EzError> {
+        let mut values = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            values.push(self.interpret(argument)?);
         }
+        Ok(values)
+    }
+
+    fn operate(&mut self, left_val: Token, operator: &Token, right_val: Token) -> Result<Token, EzError> {
+        match operator {
+            Token::Comparison(Compare::In) => {
+                return self.contains_value(&right_val, &left_val).map(Token::Boolean)
+            }
+            Token::Comparison(Compare::NotIn) => {
+                return self.contains_value(&right_val, &left_val).map(|found| Token::Boolean(!found))
+            }
+            _ => {}
+        }
+        if matches!(left_val, Token::Object(_)) || matches!(right_val, Token::Object(_)) {
+            if let Some(result) = self.operate_protocol(&left_val, operator, &right_val)? {
+                return Ok(result);
+            }
+        }
+        if is_number(&left_val) && is_number(&right_val) {
+            let result = if is_integer(&left_val) && is_integer(&right_val) {
+                self.integer_operate(&left_val, operator, &right_val)?
+            } else {
+                self.float_operate(&left_val, operator, &right_val)?
+            };
+            if let Some(token) = result {
+                return Ok(token);
+            }
+        }
+        let unexpected_values = || {
+            EzError::new(
+                "i003",
+                format!(
+                    "unsupported operand types for {}: {} and {}",
+                    operator_symbol(operator),
+                    type_name(&left_val),
+                    type_name(&right_val)
+                ),
+            )
+        };
+        let token = match operator.clone() {
+            Token::Plus => match (left_val.clone(), right_val.clone()) {
+                (Token::String(left_str), Token::String(right_str)) => {
+                    Token::String(format!("{}{}", left_str, right_str))
+                }
+                // Numbers and booleans on either side of a string are shown as by `print`
+                (Token::String(left_str), scalar) if is_scalar(&scalar) => {
+                    Token::String(format!("{}{}", left_str, display(&scalar)?))
+                }
+                (scalar, Token::String(right_str)) if is_scalar(&scalar) => {
+                    Token::String(format!("{}{}", display(&scalar)?, right_str))
+                }
+                (Token::List(mut left_list), Token::List(right_list)) => {
+                    check_sequence_length(left_list.len().saturating_add(right_list.len()))?;
+                    left_list.extend(right_list);
+                    Token::List(left_list)
+                }
+                // Durations are numbers of seconds
+                (Token::DateTime(time), seconds) | (seconds, Token::DateTime(time))
+                    if is_number(&seconds) =>
+                {
+                    Token::DateTime(time.add_seconds(to_float(&seconds))?)
+                }
+                _ => return Err(unexpected_values()),
+            },
+            Token::Minus => match (&left_val, &right_val) {
+                (Token::DateTime(later), Token::DateTime(earlier)) => {
+                    Token::Float(later.seconds_since(earlier))
+                }
+                (Token::DateTime(time), seconds) if is_number(seconds) => {
+                    Token::DateTime(time.add_seconds(-to_float(seconds))?)
+                }
+                _ => return Err(unexpected_values()),
+            },
+            // Sequences repeat, a count below one gives an empty sequence
+            Token::Asterisk => match (left_val.clone(), right_val.clone()) {
+                (Token::String(text), count) | (count, Token::String(text)) if is_integer(&count) => {
+                    let count = repeat_count(&count, text.len())?;
+                    Token::String(text.repeat(count))
+                }
+                (Token::List(values), count) | (count, Token::List(values)) if is_integer(&count) => {
+                    let count = repeat_count(&count, values.len())?;
+                    Token::List(values.iter().cycle().take(values.len() * count).cloned().collect())
+                }
+                _ => return Err(unexpected_values()),
+            },
+            // Booleans combine without short-circuiting
+            Token::Ampersand | Token::Pipe | Token::Caret => match (&left_val, &right_val) {
+                (Token::Boolean(left), Token::Boolean(right)) => Token::Boolean(match operator {
+                    Token::Ampersand => left & right,
+                    Token::Pipe => left | right,
+                    _ => left ^ right,
+                }),
+                _ => return Err(unexpected_values()),
+            },
+            // Only numbers support arithmetic, booleans are not numbers
+            Token::DoubleAsterisk
+            | Token::ShiftLeft
+            | Token::ShiftRight
+            | Token::Slash
+            | Token::DoubleSlash
+            | Token::Modulo => return Err(unexpected_values()),
+            Token::Comparison(operator) => match (operator, &left_val, &right_val) {
+                (Compare::Equal, _, _) => Token::Boolean(values_equal(&left_val, &right_val)),
+                (Compare::NotEqual, _, _) => Token::Boolean(!values_equal(&left_val, &right_val)),
+                // Strings compare lexicographically by character
+                (compare, Token::String(left_str), Token::String(right_str)) => {
+                    Token::Boolean(compare_ordering(&compare, left_str.cmp(right_str)))
+                }
+                (compare, Token::DateTime(left), Token::DateTime(right)) => {
+                    Token::Boolean(compare_ordering(&compare, left.cmp(right)))
+                }
+                // Only numbers, strings and dates are ordered
+                _ => return Err(unexpected_values()),
+            },
+            _ => {
+                return Err(EzError::new(
+                    "i004",
+                    format!("Unexpected operator: {}", operator_symbol(operator)),
+                ))
+            }
+        };
+        Ok(token)
+    }
+
+    // Integers stay on the i64 fast path and switch to BigInt only on overflow.
+    // The arithmetic is always checked, so debug and release builds behave the same.
+    fn integer_operate(
+        &self,
+        left_val: &Token,
+        operator: &Token,
+        right_val: &Token,
+    ) -> Result<Option<Token>, EzError> {
+        if *operator == Token::Slash {
+            // True division always produces a float. Integers a float holds exactly are
+            // divided as floats, larger ones are rounded once from the exact quotient.
+            const EXACT_FLOAT_INTEGER: u64 = 1 << 53;
+            if let (Token::Integer(left), Token::Integer(right)) = (left_val, right_val) {
+                if left.unsigned_abs() <= EXACT_FLOAT_INTEGER
+                    && right.unsigned_abs() <= EXACT_FLOAT_INTEGER
+                {
+                    return self.float_operate(left_val, operator, right_val);
+                }
+            }
+            let quotient = to_big_integer(left_val)
+                .true_div(&to_big_integer(right_val))
+                .ok_or_else(division_by_zero)?;
+            if quotient.is_infinite() {
+                return Err(EzError::new(
+                    "i013",
+                    String::from("Integer division result too large for a float"),
+                ));
+            }
+            return Ok(Some(Token::Float(quotient)));
+        }
+        if let (Token::Integer(left), Token::Integer(right)) = (left_val, right_val) {
+            let (left, right) = (*left, *right);
+            let result = match operator {
+                Token::Plus => left.checked_add(right),
+                Token::Minus => left.checked_sub(right),
+                Token::Asterisk => left.checked_mul(right),
+                Token::DoubleSlash => {
+                    if right == 0 {
+                        return Err(division_by_zero());
+                    }
+                    floor_divide(left, right)
+                }
+                Token::Modulo => {
+                    if right == 0 {
+                        return Err(division_by_zero());
+                    }
+                    Some(floor_modulo(left, right))
+                }
+                Token::DoubleAsterisk => {
+                    if right < 0 {
+                        // Negative powers are fractions
+                        return self.float_operate(left_val, operator, right_val);
+                    }
+                    u32::try_from(right).ok().and_then(|exponent| left.checked_pow(exponent))
+                }
+                Token::Ampersand => Some(left & right),
+                Token::Pipe => Some(left | right),
+                Token::Caret => Some(left ^ right),
+                Token::ShiftLeft => {
+                    if right < 0 {
+                        return Err(negative_shift_count());
+                    }
+                    // Fast path only while no bits are lost
+                    (right < 63)
+                        .then(|| left << right)
+                        .filter(|shifted| shifted >> right == left)
+                }
+                Token::ShiftRight => {
+                    if right < 0 {
+                        return Err(negative_shift_count());
+                    }
+                    Some(left >> right.min(63))
+                }
+                Token::Comparison(compare) => {
+                    return Ok(Some(Token::Boolean(compare_ordering(compare, left.cmp(&right)))))
+                }
+                _ => return Ok(None),
+            };
+            if let Some(value) = result {
+                return Ok(Some(Token::Integer(value)));
+            }
+        }
+
+        let left = to_big_integer(left_val);
+        let right = to_big_integer(right_val);
+        if left.bit_length().max(right.bit_length()) >= MAX_INTEGER_BITS
+            || (*operator == Token::Asterisk
+                && left.bit_length() + right.bit_length() > MAX_INTEGER_BITS)
+        {
+            return Err(EzError::new("i013", String::from("Integer too large")));
+        }
+        let result = match operator {
+            Token::Plus => left.add(&right),
+            Token::Minus => left.sub(&right),
+            Token::Asterisk => left.mul(&right),
+            Token::DoubleSlash | Token::Modulo => {
+                let (mut quotient, mut remainder) = match left.div_rem(&right) {
+                    Some(result) => result,
+                    None => return Err(division_by_zero()),
+                };
+                // Round towards negative infinity, the remainder takes the divisor's sign
+                if !remainder.is_zero() && remainder.is_negative() != right.is_negative() {
+                    quotient = quotient.sub(&BigInt::from_i64(1));
+                    remainder = remainder.add(&right);
+                }
+                if *operator == Token::DoubleSlash {
+                    quotient
+                } else {
+                    remainder
+                }
+            }
+            Token::DoubleAsterisk => {
+                if right.is_negative() {
+                    return self.float_operate(left_val, operator, right_val);
+                }
+                integer_power(&left, &right)?
+            }
+            Token::Ampersand => left.bitand(&right),
+            Token::Pipe => left.bitor(&right),
+            Token::Caret => left.bitxor(&right),
+            Token::ShiftLeft => {
+                if right.is_negative() {
+                    return Err(negative_shift_count());
+                }
+                match right.to_i64() {
+                    Some(shift) if left.bit_length() + (shift as usize) <= MAX_INTEGER_BITS => {
+                        left.shl(shift as usize)
+                    }
+                    _ if left.is_zero() => left,
+                    _ => return Err(EzError::new("i013", String::from("Integer too large"))),
+                }
+            }
+            Token::ShiftRight => {
+                if right.is_negative() {
+                    return Err(negative_shift_count());
+                }
+                // Shifting out every bit leaves 0, or -1 for negative numbers
+                let shift = right.to_i64().map_or(usize::MAX, |shift| shift as usize);
+                left.shr(shift)
+            }
+            Token::Comparison(compare) => {
+                return Ok(Some(Token::Boolean(compare_ordering(compare, left.cmp(&right)))))
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(integer_token(result)))
+    }
+
+    // Any operation involving a float, and true division, follows IEEE 754 except
+    // that dividing by zero raises ZeroDivisionError like the integer operations
+    fn float_operate(
+        &self,
+        left_val: &Token,
+        operator: &Token,
+        right_val: &Token,
+    ) -> Result<Option<Token>, EzError> {
+        if let Token::Comparison(compare) = operator {
+            // Comparisons never overflow, and NaN is unordered
+            let result = match compare_numbers(left_val, right_val) {
+                Some(ordering) => compare_ordering(compare, ordering),
+                None => *compare == Compare::NotEqual,
+            };
+            return Ok(Some(Token::Boolean(result)));
+        }
+        let left = self.checked_float(left_val)?;
+        let right = self.checked_float(right_val)?;
+        let result = match operator {
+            Token::Plus => left + right,
+            Token::Minus => left - right,
+            Token::Asterisk => left * right,
+            Token::Slash | Token::DoubleSlash | Token::Modulo if right == 0.0 => {
+                return Err(division_by_zero())
+            }
+            Token::DoubleAsterisk => {
+                if left == 0.0 && right < 0.0 {
+                    return Err(division_by_zero());
+                }
+                if left < 0.0 && right.fract() != 0.0 && right.is_finite() {
+                    return Err(EzError::new(
+                        "i018",
+                        String::from("Negative number raised to a fractional power"),
+                    ));
+                }
+                let result = left.powf(right);
+                if result.is_infinite() && left.is_finite() && right.is_finite() {
+                    return Err(EzError::new("i013", String::from("Float result too large")));
+                }
+                result
+            }
+            Token::Slash => left / right,
+            Token::DoubleSlash => (left / right).floor(),
+            Token::Modulo => {
+                let remainder = left % right;
+                if remainder == 0.0 {
+                    // A zero remainder takes the sign of the divisor too, -0.0 % 5 is 0.0
+                    0.0f64.copysign(right)
+                } else if (remainder < 0.0) != (right < 0.0) {
+                    remainder + right
+                } else {
+                    remainder
+                }
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(Token::Float(result)))
+    }
+
+    fn checked_float(&self, token: &Token) -> Result<f64, EzError> {
+        let float = to_float(token);
+        if float.is_infinite() && is_integer(token) {
+            return Err(EzError::new(
+                "i013",
+                String::from("Integer too large to convert to float"),
+            ));
+        }
+        Ok(float)
+    }
+}
+
+// Zero, empty strings and none are false, every other value is true
+fn is_truthy(token: &Token) -> bool {
+    match token {
+        Token::Boolean(value) => *value,
+        Token::Integer(value) => *value != 0,
+        Token::BigInteger(value) => !value.is_zero(),
+        Token::Float(value) => *value != 0.0,
+        Token::String(value) => !value.is_empty(),
+        Token::List(values) => !values.is_empty(),
+        Token::None => false,
+        _ => true,
+    }
+}
+
+// Method a class defines for the operator, and the reflected method of the right operand
+fn protocol_methods(operator: &Token) -> Option<(&'static str, &'static str)> {
+    match operator {
+        Token::Plus => Some(("__add__", "__radd__")),
+        Token::Minus => Some(("__sub__", "__rsub__")),
+        Token::Asterisk => Some(("__mul__", "__rmul__")),
+        Token::Slash => Some(("__truediv__", "__rtruediv__")),
+        Token::DoubleSlash => Some(("__floordiv__", "__rfloordiv__")),
+        Token::Modulo => Some(("__mod__", "__rmod__")),
+        Token::DoubleAsterisk => Some(("__pow__", "__rpow__")),
+        Token::Comparison(compare) => match compare {
+            Compare::Equal => Some(("__eq__", "__eq__")),
+            Compare::NotEqual => Some(("__ne__", "__ne__")),
+            Compare::LessThan => Some(("__lt__", "__gt__")),
+            Compare::GreaterThan => Some(("__gt__", "__lt__")),
+            Compare::LessThanOrEqual => Some(("__le__", "__ge__")),
+            Compare::GreaterThanOrEqual => Some(("__ge__", "__le__")),
+            Compare::In | Compare::NotIn => None,
+        },
+        _ => None,
+    }
+}
+
+fn protocol_text(method: &str, value: Token) -> Result<String, EzError> {
+    match value {
+        Token::String(text) => Ok(text),
+        _ => Err(EzError::new(
+            "i003",
+            format!("{} must return a string, got {}", method, type_name(&value)),
+        )),
+    }
+}
+
+fn holds_object(token: &Token) -> bool {
+    match token {
+        Token::Object(_) => true,
+        Token::List(values) => values.iter().any(holds_object),
+        _ => false,
+    }
+}
+
+// Modules built into the interpreter, with their functions and constants
+fn builtin_module(name: &str) -> Option<HashMap<String, Token>> {
+    let (module, functions, constants) = match name {
+        "math" => ("math", math::FUNCTIONS, math::constants()),
+        "random" => ("random", random::FUNCTIONS, Vec::new()),
+        "time" => ("time", time::FUNCTIONS, Vec::new()),
+        "files" => ("files", files::FUNCTIONS, Vec::new()),
+        _ => return None,
+    };
+    let mut names: HashMap<String, Token> = constants
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect();
+    for function in functions {
+        names.insert(function.to_string(), Token::Native(Native { module, name: function }));
+    }
+    Some(names)
+}
+
+// Name of the value's type for error messages
+pub(crate) fn type_name(token: &Token) -> &'static str {
+    match token {
+        Token::None => "None",
+        Token::Boolean(_) => "boolean",
+        Token::Integer(_) | Token::BigInteger(_) => "integer",
+        Token::Float(_) => "float",
+        Token::String(_) => "string",
+        Token::List(_) => "list",
+        Token::Error(_) => "error",
+        Token::Function(_) | Token::Native(_) => "function",
+        Token::Type(_) => "class",
+        Token::Object(_) => "object",
+        Token::Module(_) => "module",
+        Token::DateTime(_) => "datetime",
+        Token::File(_) => "file",
+        _ => "value",
+    }
+}
+
+// Source text of an operator for error messages
+fn operator_symbol(operator: &Token) -> &'static str {
+    match operator {
+        Token::Plus => "+",
+        Token::Minus => "-",
+        Token::Asterisk => "*",
+        Token::DoubleAsterisk => "**",
+        Token::Slash => "/",
+        Token::DoubleSlash => "//",
+        Token::Modulo => "%",
+        Token::Ampersand => "&",
+        Token::Pipe => "|",
+        Token::Caret => "^",
+        Token::Tilde => "~",
+        Token::ShiftLeft => "<<",
+        Token::ShiftRight => ">>",
+        Token::And => "and",
+        Token::Or => "or",
+        Token::Not => "not",
+        Token::Comparison(compare) => match compare {
+            Compare::Equal => "==",
+            Compare::NotEqual => "!=",
+            Compare::GreaterThan => ">",
+            Compare::LessThan => "<",
+            Compare::GreaterThanOrEqual => ">=",
+            Compare::LessThanOrEqual => "<=",
+            Compare::In => "in",
+            Compare::NotIn => "not in",
+        },
+        _ => "?",
+    }
+}
+
+// Types of the arguments of a call, `integer, string`
+pub(crate) fn type_names(arguments: &[Token]) -> String {
+    arguments.iter().map(type_name).collect::<Vec<_>>().join(", ")
+}
+
+// Built-in error types, other error types are classes deriving from one of them
+fn is_error_type(name: &str) -> bool {
+    matches!(
+        name,
+        "Exception"
+            | "NameError"
+            | "TypeError"
+            | "IndentationError"
+            | "AssertionError"
+            | "OverflowError"
+            | "ZeroDivisionError"
+            | "IndexError"
+            | "AttributeError"
+            | "ValueError"
+            | "SyntaxError"
+            | "RuntimeError"
+            | "RecursionError"
+            | "ImportError"
+            | "OSError"
+            | "StopIteration"
+    )
+}
+
+fn is_integer(token: &Token) -> bool {
+    matches!(token, Token::Integer(_) | Token::BigInteger(_))
+}
+
+fn is_number(token: &Token) -> bool {
+    is_integer(token) || matches!(token, Token::Float(_))
+}
+
+fn is_scalar(token: &Token) -> bool {
+    is_number(token) || matches!(token, Token::Boolean(_))
+}
+
+fn to_float(token: &Token) -> f64 {
+    match token {
+        Token::Integer(value) => *value as f64,
+        Token::BigInteger(value) => value.to_f64(),
+        Token::Float(value) => *value,
+        _ => 0.0,
+    }
+}
+
+// Exact order of two numbers, None if one is NaN. Integers are not rounded to a
+// float, 2 ** 53 + 1 is greater than 9007199254740992.0.
+pub(crate) fn compare_numbers(left: &Token, right: &Token) -> Option<Ordering> {
+    match (left, right) {
+        (Token::Float(left), Token::Float(right)) => left.partial_cmp(right),
+        (Token::Float(float), integer) => {
+            compare_integer_float(integer, *float).map(Ordering::reverse)
+        }
+        (integer, Token::Float(float)) => compare_integer_float(integer, *float),
+        _ => Some(to_big_integer(left).cmp(&to_big_integer(right))),
+    }
+}
+
+fn compare_integer_float(integer: &Token, float: f64) -> Option<Ordering> {
+    if float.is_infinite() {
+        return Some(if float > 0.0 { Ordering::Less } else { Ordering::Greater });
+    }
+    // An integer equal to the whole part is ordered by the fraction alone
+    match to_big_integer(integer).cmp(&BigInt::from_f64(float)?) {
+        Ordering::Equal => 0.0.partial_cmp(&float.fract()),
+        ordering => Some(ordering),
+    }
+}
+
+// Integer division rounding towards negative infinity, None on overflow
+fn floor_divide(left: i64, right: i64) -> Option<i64> {
+    let quotient = left.checked_div(right)?;
+    if left % right != 0 && (left < 0) != (right < 0) {
+        Some(quotient - 1)
+    } else {
+        Some(quotient)
+    }
+}
+
+// Remainder with the sign of the divisor
+fn floor_modulo(left: i64, right: i64) -> i64 {
+    // Only i64::MIN % -1 overflows, and its remainder is zero
+    let remainder = left.checked_rem(right).unwrap_or(0);
+    if remainder != 0 && (remainder < 0) != (right < 0) {
+        remainder + right
+    } else {
+        remainder
+    }
+}
+
+// Text shown by print and inside strings
+pub(crate) fn display(token: &Token) -> Result<String, EzError> {
+    display_nested(token, false)
+}
+
+// Objects inside an object are shortened to `Name(...)`, so cycles end
+fn display_nested(token: &Token, in_object: bool) -> Result<String, EzError> {
+    match token {
+        Token::Integer(value) => Ok(value.to_string()),
+        Token::BigInteger(value) => Ok(value.to_string()),
+        Token::Float(value) => Ok(format_float(*value)),
+        Token::Boolean(value) => Ok(value.to_string()),
+        Token::String(value) => Ok(value.clone()),
+        Token::List(values) => {
+            let mut parts = Vec::with_capacity(values.len());
+            for value in values {
+                // Strings inside a list are shown quoted
+                parts.push(display_item(value, in_object)?);
+            }
+            Ok(format!("[{}]", parts.join(", ")))
+        }
+        Token::None => Ok(String::from("None")),
+        Token::Error(error) => Ok(error.message.clone()),
+        Token::Function(function) => Ok(format!("<function {}>", function.name)),
+        Token::Native(native) => Ok(format!("<built-in function {}>", native.name)),
+        Token::Type(class) => Ok(format!("<class {}>", class.name)),
+        Token::Module(module) => Ok(format!("<module {}>", module.name)),
+        Token::DateTime(time) => Ok(time.to_string()),
+        Token::File(file) => Ok(file.to_string()),
+        Token::Object(object) if in_object => Ok(format!("{}(...)", object.class().name)),
+        Token::Object(object) => {
+            let instance = object.0.borrow();
+            let mut parts = Vec::with_capacity(instance.fields.len());
+            for (name, value) in &instance.fields {
+                parts.push(format!("{}={}", name, display_item(value, true)?));
+            }
+            Ok(format!("{}({})", instance.class.name, parts.join(", ")))
+        }
+        _ => Err(EzError::new(
+            "i009",
+            format!("{} value cannot be shown", type_name(token)),
+        )),
+    }
+}
+
+// Strings inside a list or object are shown quoted
+fn display_item(token: &Token, in_object: bool) -> Result<String, EzError> {
+    match token {
+        Token::String(text) => Ok(format!("{:?}", text)),
+        _ => display_nested(token, in_object),
+    }
+}
+
+// Equality that treats 1 and 1.0 as the same value, also inside lists
+fn values_equal(left: &Token, right: &Token) -> bool {
+    match (left, right) {
+        (Token::List(left_values), Token::List(right_values)) => {
+            left_values.len() == right_values.len()
+                && left_values
+                    .iter()
+                    .zip(right_values)
+                    .all(|(left, right)| values_equal(left, right))
+        }
+        _ if is_integer(left) && is_integer(right) => to_big_integer(left) == to_big_integer(right),
+        _ if is_number(left) && is_number(right) => {
+            compare_numbers(left, right) == Some(Ordering::Equal)
+        }
+        _ => left == right,
+    }
+}
+
+// `needle in haystack`, substrings for strings and elements for lists
+fn contains(haystack: &Token, needle: &Token) -> Result<bool, EzError> {
+    match (haystack, needle) {
+        (Token::String(text), Token::String(part)) => Ok(text.contains(part.as_str())),
+        (Token::List(values), _) => Ok(values.iter().any(|value| values_equal(value, needle))),
+        _ => Err(EzError::new(
+            "i003",
+            format!(
+                "unsupported operand types for in: {} and {}",
+                type_name(needle),
+                type_name(haystack)
+            ),
+        )),
+    }
+}
+
+fn check_sequence_length(length: usize) -> Result<(), EzError> {
+    if length > MAX_SEQUENCE_LENGTH {
+        return Err(EzError::new("i013", String::from("Sequence too large")));
+    }
+    Ok(())
+}
+
+fn repeat_count(count: &Token, length: usize) -> Result<usize, EzError> {
+    let count = match count {
+        Token::Integer(value) if *value <= 0 => return Ok(0),
+        Token::Integer(value) => *value as usize,
+        Token::BigInteger(value) if value.is_negative() => return Ok(0),
+        _ => usize::MAX,
+    };
+    if length > 0 {
+        check_sequence_length(length.saturating_mul(count))?;
+    }
+    Ok(count)
+}
+
+fn cannot_assign(target: &ASTNode) -> EzError {
+    let kind = match target {
+        ASTNode::Integer(..)
+        | ASTNode::BigInteger(..)
+        | ASTNode::Float(..)
+        | ASTNode::Boolean(..)
+        | ASTNode::String(..)
+        | ASTNode::FormatString(..) => "literal",
+        ASTNode::FunctionCall { .. } | ASTNode::MethodCall { .. } => "function call",
+        _ => "expression",
+    };
+    EzError::new("i005", format!("Cannot assign to {}", kind))
+}
+
+fn takes_no_arguments(name: &str, arguments: &[Token]) -> EzError {
+    EzError::new(
+        "i017",
+        format!("{} takes no arguments but {} were given", name, arguments.len()),
+    )
+}
+
+fn no_attribute(object: &Token, name: &str) -> EzError {
+    match object {
+        Token::Error(error) => EzError::new(
+            "i016",
+            format!("{} has no attribute: {}", error.kind, name),
+        ),
+        Token::Object(instance) => EzError::new(
+            "i016",
+            format!("{} object has no attribute: {}", instance.class().name, name),
+        ),
+        Token::Type(class) => EzError::new(
+            "i016",
+            format!("Class {} has no attribute: {}", class.name, name),
+        ),
+        Token::Module(module) => EzError::new(
+            "i016",
+            format!("Module {} has no attribute: {}", module.name, name),
+        ),
+        Token::DateTime(_) => EzError::new("i016", format!("Date has no attribute: {}", name)),
+        Token::File(_) => EzError::new("i016", format!("File has no attribute: {}", name)),
+        _ => EzError::new(
+            "i016",
+            format!("{} value has no attribute: {}", type_name(object), name),
+        ),
+    }
+}
+
+fn not_subscriptable(object: &Object) -> EzError {
+    EzError::new(
+        "i003",
+        format!("{} object cannot be indexed", object.class().name),
+    )
+}
+
+// Splits a list or string into exactly `count` values
+fn unpack(value: Token, count: usize) -> Result<Vec<Token>, EzError> {
+    let values = match value {
+        Token::List(values) => values,
+        Token::String(text) => text.chars().map(|c| Token::String(c.to_string())).collect(),
+        _ => {
+            return Err(EzError::new(
+                "i003",
+                format!("Cannot unpack {} value", type_name(&value)),
+            ))
+        }
+    };
+    if values.len() > count {
+        return Err(EzError::new(
+            "i018",
+            format!("Too many values to unpack, expected {}", count),
+        ));
+    }
+    if values.len() < count {
+        return Err(EzError::new(
+            "i018",
+            format!("Not enough values to unpack, expected {}, got {}", count, values.len()),
+        ));
+    }
+    Ok(values)
+}
+
+// Replaces the element at the path of indexes, lists are changed in place
+fn set_item(container: &mut Token, indexes: &[Token], value: Token) -> Result<(), EzError> {
+    match container {
+        Token::List(values) => {
+            let position = resolve_index(&indexes[0], values.len())?.ok_or_else(|| {
+                let index = display(&indexes[0]).unwrap_or_default();
+                EzError::new("i015", format!("Index out of range: {}", index))
+            })?;
+            if indexes.len() == 1 {
+                values[position] = value;
+                Ok(())
+            } else {
+                set_item(&mut values[position], &indexes[1..], value)
+            }
+        }
+        Token::String(_) => Err(EzError::new(
+            "i003",
+            String::from("Strings cannot be changed by index"),
+        )),
+        _ => Err(EzError::new(
+            "i003",
+            format!("{} value cannot be indexed", type_name(container)),
+        )),
+    }
+}
+
+// Resolves a possibly negative index against the length, None if out of range
+fn resolve_index(index: &Token, length: usize) -> Result<Option<usize>, EzError> {
+    let index = match index {
+        Token::Integer(value) => *value,
+        Token::BigInteger(_) => return Ok(None),
+        _ => {
+            return Err(EzError::new(
+                "i003",
+                format!("Index must be an integer, got {}", type_name(index)),
+            ))
+        }
+    };
+    let resolved = if index < 0 {
+        index.checked_add(length as i64)
+    } else {
+        Some(index)
+    };
+    Ok(resolved.filter(|value| (0..length as i64).contains(value)).map(|value| value as usize))
+}
+
+fn index_value(value: Token, index: Token) -> Result<Token, EzError> {
+    let out_of_range = || {
+        let index = display(&index).unwrap_or_default();
+        EzError::new("i015", format!("Index out of range: {}", index))
+    };
+    match value {
+        Token::String(text) => {
+            let position = resolve_index(&index, text.chars().count())?.ok_or_else(out_of_range)?;
+            Ok(Token::String(text.chars().skip(position).take(1).collect()))
+        }
+        Token::List(mut values) => {
+            let position = resolve_index(&index, values.len())?.ok_or_else(out_of_range)?;
+            Ok(values.swap_remove(position))
+        }
+        _ => Err(EzError::new(
+            "i003",
+            format!("{} value cannot be indexed", type_name(&value)),
+        )),
+    }
+}
+
+// Slice bounds are clamped to the sequence like in Python
+fn slice_bound(bound: Option<Token>, length: usize, default: usize) -> Result<usize, EzError> {
+    let bound = match bound {
+        Some(Token::Integer(value)) => value,
+        Some(Token::BigInteger(value)) => {
+            return Ok(if value.is_negative() { 0 } else { length })
+        }
+        Some(token) => {
+            return Err(EzError::new(
+                "i003",
+                format!("Slice index must be an integer, got {}", type_name(&token)),
+            ))
+        }
+        None => return Ok(default),
+    };
+    let length = length as i64;
+    let bound = if bound < 0 { bound.saturating_add(length) } else { bound };
+    Ok(bound.clamp(0, length) as usize)
+}
+
+fn slice_value(value: Token, start: Option<Token>, end: Option<Token>) -> Result<Token, EzError> {
+    match value {
+        Token::String(text) => {
+            let length = text.chars().count();
+            let start = slice_bound(start, length, 0)?;
+            let end = slice_bound(end, length, length)?;
+            Ok(Token::String(
+                text.chars().skip(start).take(end.saturating_sub(start)).collect(),
+            ))
+        }
+        Token::List(values) => {
+            let length = values.len();
+            let start = slice_bound(start, length, 0)?;
+            let end = slice_bound(end, length, length)?.max(start);
+            Ok(Token::List(values[start..end].to_vec()))
+        }
+        _ => Err(EzError::new(
+            "i003",
+            format!("{} value cannot be sliced", type_name(&value)),
+        )),
+    }
+}
+
+// Python-style float text: 3.0, 0.1, 1e+16, inf, nan
+fn format_float(value: f64) -> String {
+    if value.is_nan() {
+        String::from("nan")
+    } else if value.is_infinite() {
+        String::from(if value > 0.0 { "inf" } else { "-inf" })
+    } else if value != 0.0 && (value.abs() >= 1e16 || value.abs() < 1e-4) {
+        let scientific = format!("{:e}", value);
+        let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+        let exponent: i32 = exponent.parse().unwrap_or(0);
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{}e{}{:02}", mantissa, sign, exponent.abs())
+    } else if value.fract() == 0.0 {
+        format!("{:.1}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+pub(crate) fn to_big_integer(token: &Token) -> BigInt {
+    match token {
+        Token::Integer(value) => BigInt::from_i64(*value),
+        Token::BigInteger(value) => value.clone(),
+        _ => BigInt::from_i64(0),
+    }
+}
+
+// Results that fit into i64 go back to the fast representation
+pub(crate) fn integer_token(value: BigInt) -> Token {
+    match value.to_i64() {
+        Some(small) => Token::Integer(small),
+        None => Token::BigInteger(value),
+    }
+}
+
+// Exact integer power, the exponent is never negative
+fn integer_power(base: &BigInt, exponent: &BigInt) -> Result<BigInt, EzError> {
+    let one = BigInt::from_i64(1);
+    // 0, 1 and -1 stay small for any exponent
+    if base.bit_length() <= 1 {
+        return Ok(match (base.is_zero(), exponent.is_zero()) {
+            (_, true) => one,
+            (true, false) => base.clone(),
+            _ if base.is_negative() && exponent.is_odd() => base.clone(),
+            _ => one,
+        });
+    }
+    // The result has at least (bits - 1) * exponent + 1 bits
+    let exponent = match exponent.to_i64() {
+        Some(exponent)
+            if (base.bit_length() as i64 - 1).saturating_mul(exponent) < MAX_INTEGER_BITS as i64 =>
+        {
+            exponent
+        }
+        _ => return Err(EzError::new("i013", String::from("Integer too large"))),
+    };
+    // Square and multiply
+    let mut result = one;
+    let mut square = base.clone();
+    let mut remaining = exponent;
+    while remaining > 0 {
+        if remaining & 1 == 1 {
+            result = result.mul(&square);
+        }
+        remaining >>= 1;
+        if remaining > 0 {
+            square = square.mul(&square);
+        }
+    }
+    Ok(result)
+}
+
+fn negative_shift_count() -> EzError {
+    EzError::new("i018", String::from("Negative shift count"))
+}
+
+fn division_by_zero() -> EzError {
+    EzError::new("i014", String::from("Division by zero"))
+}
+
+fn compare_ordering(compare: &Compare, ordering: Ordering) -> bool {
+    match compare {
+        Compare::Equal => ordering == Ordering::Equal,
+        Compare::NotEqual => ordering != Ordering::Equal,
+        Compare::LessThan => ordering == Ordering::Less,
+        Compare::LessThanOrEqual => ordering != Ordering::Greater,
+        Compare::GreaterThan => ordering == Ordering::Greater,
+        Compare::GreaterThanOrEqual => ordering != Ordering::Less,
+        // Membership is not an ordering, operate handles it first
+        Compare::In | Compare::NotIn => false,
     }
 }
@@ -0,0 +1,64 @@
+pub mod bigint;
+pub mod error;
+pub mod files;
+pub mod interpreter;
+pub mod math;
+pub mod objects;
+pub mod parser;
+pub mod random;
+pub mod strings;
+pub mod test_runner;
+pub mod time;
+pub mod tokenizer;
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::error::EzError;
+use crate::interpreter::*;
+use crate::parser::*;
+use crate::tokenizer::*;
+
+// Nested expressions and function calls recurse, the `ezlang` binary runs the
+// interpreter on a thread with this much stack
+pub const STACK_SIZE: usize = 256 << 20;
+
+pub fn read_file_to_string(file_path: &str) -> io::Result<String> {
+    let path = Path::new(file_path);
+    let mut file = File::open(path)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+pub fn run_source(
+    interpreter: &mut Interpreter,
+    content: &str,
+    debugger: bool,
+) -> Result<(), EzError> {
+    let mut parser = Parser::new(Tokenizer::new(content));
+    interpreter.set_source(content);
+    loop {
+        let ast = match parser.parse() {
+            Ok(Some(ast)) => ast,
+            Ok(None) => break,
+            Err(error) => {
+                let mut frame = interpreter.frame(error.line);
+                frame.column = parser.column();
+                return Err(error.with_frame(frame));
+            }
+        };
+        if debugger {
+            println!("__________________");
+            println!("Line {}: {}", parser.line(), content.lines().nth(parser.line() - 1).unwrap_or(""));
+            println!("AST: {:#?}", ast);
+            println!("{:?}", interpreter);
+        }
+        let result = interpreter.interpret_line(&ast, parser.line())?;
+        if debugger {
+            println!("Result: {:?}", result);
+        }
+    }
+    interpreter.finish()
+}
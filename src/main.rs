@@ -1,70 +1,83 @@
-pub mod interpreter;
-pub mod parser;
-pub mod tokenizer;
-
 use std::env;
-use std::fs::File;
-use std::io::{self, Read};
 use std::path::Path;
+use std::process;
+use std::thread;
 
-use crate::interpreter::*;
-use crate::parser::*;
-use crate::tokenizer::*;
-
-fn read_file_to_string(file_path: &str) -> io::Result<String> {
-    let path = Path::new(file_path);
-    let mut file = File::open(&path)?;
-    let mut content = String::new();
-    file.read_to_string(&mut content)?;
-    Ok(content)
-}
+use ezlang::interpreter::*;
+use ezlang::time::VirtualClock;
+use ezlang::{read_file_to_string, run_source, test_runner, STACK_SIZE};
 
-fn split_to_lines(program: &str) -> Vec<&str> {
-    program.split('\n').collect()
+fn main() {
+    let interpreter = thread::Builder::new().stack_size(STACK_SIZE).spawn(run);
+    match interpreter.map(|handle| handle.join()) {
+        Ok(Ok(())) => {}
+        _ => process::exit(101),
+    }
 }
 
-fn main() {
+fn run() {
     // Get the file path from the command line arguments
     let args: Vec<String> = env::args().collect();
     if args.len() < 2 {
-        panic!("Error: Provide a file path as an argument");
+        eprintln!("Error: Provide a file path as an argument");
+        process::exit(1);
+    }
+    if args[1] == "test" {
+        // Discover and run *_test.ez files
+        let root = args.get(2).map(String::as_str).unwrap_or(".");
+        if !test_runner::run_tests(Path::new(root)) {
+            process::exit(1);
+        }
+        return;
     }
     let file_path = &args[1];
     if !file_path.ends_with(".ez") {
-        panic!("Error: The file does not have an .ez extension");
+        eprintln!("Error: The file does not have an .ez extension");
+        process::exit(1);
     }
     let mut debugger = false;
-    if args.len() == 3 {
-        debugger = &args[2] == "debug";
+    let mut seed = None;
+    let mut virtual_clock = false;
+    let mut options = args[2..].iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "debug" => debugger = true,
+            // `time.sleep` returns at once and the clock starts at 2000-01-01 00:00:00
+            "--virtual-clock" => virtual_clock = true,
+            // Reproducible runs of programs using `random`
+            "--seed" => match options.next().and_then(|value| value.parse::<i64>().ok()) {
+                Some(value) => seed = Some(value),
+                None => {
+                    eprintln!("Error: --seed expects an integer");
+                    process::exit(1);
+                }
+            },
+            _ => {
+                eprintln!("Error: Unknown option: {}", option);
+                process::exit(1);
+            }
+        }
     }
 
     match read_file_to_string(file_path) {
         Ok(content) => {
             let mut interpreter = Interpreter::new();
-
-            for line in split_to_lines(content.as_str()) {
-                if debugger {
-                    println!("__________________");
-                    println!("Line: {}", line);
-                }
-                let tokenizer = Tokenizer::new(line);
-                if debugger {
-                    println!("{:?}", tokenizer);
-                }
-                let mut parser = Parser::new(tokenizer);
-                let ast = parser.parse();
-                if debugger {
-                    println!("AST: {:#?}", ast);
-                    println!("{:?}", interpreter);
-                }
-                let result = interpreter.interpret_line(&ast);
-                if debugger {
-                    println!("Result: {:?}", result);
-                }
+            interpreter.set_stack_size(STACK_SIZE);
+            interpreter.set_file(file_path);
+            if let Some(seed) = seed {
+                interpreter.set_seed(seed as u64);
+            }
+            if virtual_clock {
+                interpreter.set_clock(Box::new(VirtualClock::new()));
+            }
+            if let Err(error) = run_source(&mut interpreter, content.as_str(), debugger) {
+                eprintln!("{}", error.traceback());
+                process::exit(1);
             }
         }
         Err(e) => {
             eprintln!("Error: {}", e);
+            process::exit(1);
         }
     }
 }
@@ -0,0 +1,421 @@
+use std::cmp::Ordering;
+use std::f64::consts;
+
+use crate::bigint::BigInt;
+use crate::error::{argument_count, expected, EzError};
+use crate::interpreter::{compare_numbers, integer_token, to_big_integer, type_name, MAX_INTEGER_BITS};
+use crate::tokenizer::Token;
+
+// Functions of the built-in `math` module
+pub const FUNCTIONS: &[&str] = &[
+    "sqrt", "pow", "exp", "log", "log2", "log10", "sin", "cos", "tan", "asin", "acos", "atan",
+    "atan2", "abs", "floor", "ceil", "round", "min", "max", "gcd", "lcm", "factorial", "isclose",
+];
+
+pub fn constants() -> Vec<(&'static str, Token)> {
+    vec![
+        ("pi", Token::Float(consts::PI)),
+        ("e", Token::Float(consts::E)),
+        ("tau", Token::Float(consts::TAU)),
+        ("inf", Token::Float(f64::INFINITY)),
+        ("nan", Token::Float(f64::NAN)),
+    ]
+}
+
+pub fn call(function: &str, arguments: &[Token]) -> Result<Token, EzError> {
+    match function {
+        "sqrt" => {
+            let x = number(function, arguments, 1)?[0];
+            checked(x.sqrt(), &[x])
+        }
+        "pow" => {
+            let values = number(function, arguments, 2)?;
+            let (x, y) = (values[0], values[1]);
+            if x == 0.0 && y < 0.0 {
+                return Err(domain_error());
+            }
+            checked(x.powf(y), &[x, y])
+        }
+        "exp" => {
+            let x = number(function, arguments, 1)?[0];
+            checked(x.exp(), &[x])
+        }
+        "log" => match arguments {
+            [x] => logarithm(function, x).map(Token::Float),
+            [x, base] => {
+                let base = logarithm(function, base)?;
+                if base == 0.0 {
+                    return Err(EzError::new("i014", String::from("Division by zero")));
+                }
+                Ok(Token::Float(logarithm(function, x)? / base))
+            }
+            _ => Err(argument_count(function, "1 or 2", arguments)),
+        },
+        "log2" | "log10" => {
+            expect_count(function, arguments, 1)?;
+            let x = to_number(function, &arguments[0])?;
+            if x.is_finite() && x > 0.0 {
+                // The dedicated functions are exact for powers of the base
+                return Ok(Token::Float(if function == "log2" { x.log2() } else { x.log10() }));
+            }
+            let base = if function == "log2" { consts::LN_2 } else { consts::LN_10 };
+            Ok(Token::Float(logarithm(function, &arguments[0])? / base))
+        }
+        "sin" | "cos" | "tan" | "asin" | "acos" | "atan" => {
+            let x = number(function, arguments, 1)?[0];
+            let result = match function {
+                "sin" => x.sin(),
+                "cos" => x.cos(),
+                "tan" => x.tan(),
+                "asin" => x.asin(),
+                "acos" => x.acos(),
+                _ => x.atan(),
+            };
+            checked(result, &[x])
+        }
+        "atan2" => {
+            let values = number(function, arguments, 2)?;
+            let (y, x) = (values[0], values[1]);
+            Ok(Token::Float(y.atan2(x)))
+        }
+        "abs" => {
+            expect_count(function, arguments, 1)?;
+            match &arguments[0] {
+                Token::Integer(value) => Ok(match value.checked_abs() {
+                    Some(value) => Token::Integer(value),
+                    None => Token::BigInteger(BigInt::from_i64(*value).neg()),
+                }),
+                Token::BigInteger(value) if value.is_negative() => Ok(Token::BigInteger(value.neg())),
+                Token::BigInteger(value) => Ok(Token::BigInteger(value.clone())),
+                Token::Float(value) => Ok(Token::Float(value.abs())),
+                value => Err(expected(function, "a number", value)),
+            }
+        }
+        "floor" | "ceil" => {
+            expect_count(function, arguments, 1)?;
+            match &arguments[0] {
+                value @ (Token::Integer(_) | Token::BigInteger(_)) => Ok(value.clone()),
+                Token::Float(value) if function == "floor" => float_to_integer(value.floor()),
+                Token::Float(value) => float_to_integer(value.ceil()),
+                value => Err(expected(function, "a number", value)),
+            }
+        }
+        "round" => round(arguments),
+        "min" | "max" => extreme(function, arguments),
+        "gcd" | "lcm" => {
+            let mut result = BigInt::from_i64(if function == "gcd" { 0 } else { 1 });
+            for argument in arguments {
+                let value = integer(function, argument)?;
+                result = if function == "gcd" {
+                    gcd(result, value)
+                } else if result.is_zero() || value.is_zero() {
+                    BigInt::from_i64(0)
+                } else {
+                    // Dividing before multiplying keeps the division small
+                    let divisor = gcd(result.clone(), value.clone());
+                    let factor = result.div_rem(&divisor).map_or(result, |(quotient, _)| quotient);
+                    if factor.bit_length() + value.bit_length() > MAX_INTEGER_BITS + 1 {
+                        return Err(integer_too_large());
+                    }
+                    absolute(factor.mul(&value))
+                };
+            }
+            Ok(integer_token(result))
+        }
+        "factorial" => {
+            expect_count(function, arguments, 1)?;
+            let n = match &arguments[0] {
+                Token::Integer(n) if *n >= 0 => *n,
+                Token::Integer(_) => {
+                    return Err(EzError::new(
+                        "i018",
+                        String::from("factorial() not defined for negative values"),
+                    ))
+                }
+                Token::BigInteger(_) => return Err(integer_too_large()),
+                value => return Err(expected(function, "an integer", value)),
+            };
+            let mut result = BigInt::from_i64(1);
+            for factor in 2..=n {
+                result = result.mul(&BigInt::from_i64(factor));
+                if result.bit_length() > MAX_INTEGER_BITS {
+                    return Err(integer_too_large());
+                }
+            }
+            Ok(integer_token(result))
+        }
+        // isclose(a, b, rel_tol = 1e-9, abs_tol = 0.0)
+        "isclose" => {
+            if !(2..=4).contains(&arguments.len()) {
+                return Err(argument_count(function, "2 to 4", arguments));
+            }
+            let mut values = [0.0, 0.0, 1e-9, 0.0];
+            for (value, argument) in values.iter_mut().zip(arguments) {
+                *value = to_number(function, argument)?;
+            }
+            let [a, b, relative, absolute] = values;
+            if relative < 0.0 || absolute < 0.0 {
+                return Err(EzError::new(
+                    "i018",
+                    String::from("Tolerances must be non-negative"),
+                ));
+            }
+            if a == b {
+                return Ok(Token::Boolean(true));
+            }
+            if a.is_infinite() || b.is_infinite() {
+                return Ok(Token::Boolean(false));
+            }
+            let difference = (a - b).abs();
+            let tolerance = (relative * a.abs().max(b.abs())).max(absolute);
+            Ok(Token::Boolean(difference <= tolerance))
+        }
+        _ => Err(EzError::new(
+            "i016",
+            format!("Module math has no attribute: {}", function),
+        )),
+    }
+}
+
+// round(x) gives an integer, round(x, digits) a value of the type of x.
+// Halves round to the even neighbour like in Python.
+fn round(arguments: &[Token]) -> Result<Token, EzError> {
+    let (value, digits) = match arguments {
+        [value] => (value, None),
+        [value, Token::Integer(digits)] => (value, Some(*digits)),
+        [_, digits] => return Err(expected("round", "integer digits", digits)),
+        _ => return Err(argument_count("round", "1 or 2", arguments)),
+    };
+    match (value, digits) {
+        (Token::Integer(_) | Token::BigInteger(_), None) => Ok(value.clone()),
+        (Token::Integer(_) | Token::BigInteger(_), Some(digits)) if digits >= 0 => Ok(value.clone()),
+        (Token::Integer(_) | Token::BigInteger(_), Some(digits)) => {
+            let value = to_big_integer(value);
+            // Units above twice the value round it to zero
+            if digits.unsigned_abs() as f64 > (value.bit_length() + 1) as f64 * consts::LOG10_2 {
+                return Ok(Token::Integer(0));
+            }
+            let unit = power_of_ten(digits.unsigned_abs());
+            let Some((quotient, remainder)) = value.div_rem(&unit) else {
+                return Ok(Token::Integer(0));
+            };
+            let twice = absolute(remainder.add(&remainder));
+            let quotient = match twice.cmp(&unit) {
+                Ordering::Greater => away_from_zero(&quotient, &value),
+                Ordering::Equal if quotient.is_odd() => away_from_zero(&quotient, &value),
+                _ => quotient,
+            };
+            Ok(integer_token(quotient.mul(&unit)))
+        }
+        (Token::Float(value), None) => float_to_integer(round_half_even(*value)),
+        (Token::Float(value), Some(_)) if !value.is_finite() => Ok(Token::Float(*value)),
+        (Token::Float(value), Some(digits)) if digits >= 0 => {
+            // Formatting rounds the exact binary value, round(2.675, 2) is 2.67
+            if digits > 400 {
+                return Ok(Token::Float(*value));
+            }
+            let text = format!("{:.*}", digits as usize, value);
+            Ok(Token::Float(text.parse().unwrap_or(*value)))
+        }
+        (Token::Float(value), Some(digits)) => {
+            let unit = 10f64.powi(digits.saturating_neg().min(400) as i32);
+            Ok(Token::Float(round_half_even(value / unit) * unit))
+        }
+        (value, _) => Err(expected("round", "a number", value)),
+    }
+}
+
+// min and max take several values or one list of values
+fn extreme(function: &str, arguments: &[Token]) -> Result<Token, EzError> {
+    let values = match arguments {
+        [Token::List(values)] => values.as_slice(),
+        [] => return Err(argument_count(function, "at least 1", arguments)),
+        [value] => return Err(expected(function, "a list or several values", value)),
+        _ => arguments,
+    };
+    let Some(mut best) = values.first() else {
+        return Err(EzError::new(
+            "i018",
+            format!("{}() of an empty list", function),
+        ));
+    };
+    for value in &values[1..] {
+        let ordering = order(function, value, best)?;
+        if (function == "min" && ordering == Ordering::Less)
+            || (function == "max" && ordering == Ordering::Greater)
+        {
+            best = value;
+        }
+    }
+    Ok(best.clone())
+}
+
+// Numbers compare with numbers and strings with strings
+fn order(function: &str, left: &Token, right: &Token) -> Result<Ordering, EzError> {
+    match (left, right) {
+        (Token::Integer(left), Token::Integer(right)) => Ok(left.cmp(right)),
+        (Token::String(left), Token::String(right)) => Ok(left.cmp(right)),
+        (
+            Token::Integer(_) | Token::BigInteger(_) | Token::Float(_),
+            Token::Integer(_) | Token::BigInteger(_) | Token::Float(_),
+        ) => Ok(compare_numbers(left, right).unwrap_or(Ordering::Equal)),
+        _ => Err(EzError::new(
+            "i003",
+            format!(
+                "{} cannot compare {} and {}",
+                function,
+                type_name(left),
+                type_name(right)
+            ),
+        )),
+    }
+}
+
+fn power_of_ten(mut exponent: u64) -> BigInt {
+    let mut result = BigInt::from_i64(1);
+    let mut square = BigInt::from_i64(10);
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result.mul(&square);
+        }
+        exponent >>= 1;
+        if exponent > 0 {
+            square = square.mul(&square);
+        }
+    }
+    result
+}
+
+// Binary GCD, big integers only shift and subtract
+fn gcd(left: BigInt, right: BigInt) -> BigInt {
+    let (mut left, mut right) = (absolute(left), absolute(right));
+    if let (Some(mut small_left), Some(mut small_right)) = (left.to_i64(), right.to_i64()) {
+        while small_right != 0 {
+            (small_left, small_right) = (small_right, small_left % small_right);
+        }
+        return BigInt::from_i64(small_left);
+    }
+    if left.is_zero() || right.is_zero() {
+        return if left.is_zero() { right } else { left };
+    }
+    let mut shift = 0;
+    while !left.is_odd() && !right.is_odd() {
+        left = left.shr(1);
+        right = right.shr(1);
+        shift += 1;
+    }
+    while !left.is_odd() {
+        left = left.shr(1);
+    }
+    loop {
+        while !right.is_odd() {
+            right = right.shr(1);
+        }
+        if left > right {
+            std::mem::swap(&mut left, &mut right);
+        }
+        right = right.sub(&left);
+        if right.is_zero() {
+            return left.shl(shift);
+        }
+    }
+}
+
+fn absolute(value: BigInt) -> BigInt {
+    if value.is_negative() {
+        value.neg()
+    } else {
+        value
+    }
+}
+
+fn away_from_zero(quotient: &BigInt, value: &BigInt) -> BigInt {
+    let step = BigInt::from_i64(if value.is_negative() { -1 } else { 1 });
+    quotient.add(&step)
+}
+
+fn round_half_even(value: f64) -> f64 {
+    let rounded = value.round();
+    if (value - value.trunc()).abs() == 0.5 {
+        2.0 * (value / 2.0).round()
+    } else {
+        rounded
+    }
+}
+
+// Whole float to an integer, large values become big integers
+fn float_to_integer(value: f64) -> Result<Token, EzError> {
+    match BigInt::from_f64(value) {
+        Some(integer) => Ok(integer_token(integer)),
+        None if value.is_nan() => Err(EzError::new(
+            "i018",
+            String::from("Cannot convert nan to an integer"),
+        )),
+        None => Err(EzError::new(
+            "i013",
+            String::from("Cannot convert infinity to an integer"),
+        )),
+    }
+}
+
+// Natural logarithm, also of integers too large for a float
+fn logarithm(function: &str, value: &Token) -> Result<f64, EzError> {
+    if let Token::BigInteger(value) = value {
+        if !value.is_negative() && value.bit_length() > 1000 {
+            let shift = value.bit_length() - 64;
+            return Ok(value.shr(shift).to_f64().ln() + shift as f64 * consts::LN_2);
+        }
+    }
+    let x = to_number(function, value)?;
+    if x <= 0.0 {
+        return Err(domain_error());
+    }
+    Ok(x.ln())
+}
+
+// A nan from numbers is outside the domain, infinity from finite numbers is out of range
+fn checked(result: f64, inputs: &[f64]) -> Result<Token, EzError> {
+    if result.is_nan() && !inputs.iter().any(|x| x.is_nan()) {
+        return Err(domain_error());
+    }
+    if result.is_infinite() && inputs.iter().all(|x| x.is_finite()) {
+        return Err(EzError::new("i013", String::from("math range error")));
+    }
+    Ok(Token::Float(result))
+}
+
+fn number(function: &str, arguments: &[Token], count: usize) -> Result<Vec<f64>, EzError> {
+    expect_count(function, arguments, count)?;
+    arguments.iter().map(|argument| to_number(function, argument)).collect()
+}
+
+fn to_number(function: &str, token: &Token) -> Result<f64, EzError> {
+    match token {
+        Token::Integer(value) => Ok(*value as f64),
+        Token::BigInteger(value) => Ok(value.to_f64()),
+        Token::Float(value) => Ok(*value),
+        _ => Err(expected(function, "a number", token)),
+    }
+}
+
+fn integer(function: &str, token: &Token) -> Result<BigInt, EzError> {
+    match token {
+        Token::Integer(_) | Token::BigInteger(_) => Ok(to_big_integer(token)),
+        _ => Err(expected(function, "integers", token)),
+    }
+}
+
+fn expect_count(function: &str, arguments: &[Token], count: usize) -> Result<(), EzError> {
+    if arguments.len() != count {
+        return Err(argument_count(function, &count.to_string(), arguments));
+    }
+    Ok(())
+}
+
+fn domain_error() -> EzError {
+    EzError::new("i018", String::from("math domain error"))
+}
+
+fn integer_too_large() -> EzError {
+    EzError::new("i013", String::from("Integer too large"))
+}
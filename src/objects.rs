@@ -0,0 +1,165 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::parser::ASTNode;
+use crate::tokenizer::Token;
+
+// File a statement comes from, its text gives the source lines of traceback frames
+#[derive(Clone)]
+pub struct Source {
+    pub file: String,
+    pub text: Rc<str>,
+}
+
+impl fmt::Debug for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Source({:?})", self.file)
+    }
+}
+
+// Function defined with `def`, its body statements are kept with their line numbers
+pub struct Function {
+    pub name: String,
+    pub parameters: Vec<String>,
+    pub body: Vec<(ASTNode, usize)>,
+    pub source: Source,
+}
+
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Function({})", self.name)
+    }
+}
+
+// Functions, classes, modules and objects are equal only to themselves
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// Class defined with `class`. Classes deriving from an error type create error values,
+// `error` is the built-in error type at the root of their bases.
+pub struct Class {
+    pub name: String,
+    pub base: Option<Rc<Class>>,
+    pub attributes: HashMap<String, Token>,
+    pub error: Option<String>,
+}
+
+impl Class {
+    // Looks the attribute up in the class and its bases, with the class defining it
+    pub fn find(self: &Rc<Self>, name: &str) -> Option<(Rc<Class>, Token)> {
+        let mut class = Some(self);
+        while let Some(current) = class {
+            if let Some(value) = current.attributes.get(name) {
+                return Some((current.clone(), value.clone()));
+            }
+            class = current.base.as_ref();
+        }
+        None
+    }
+
+    // Whether the class is `other` or derives from it
+    pub fn derives_from(self: &Rc<Self>, other: &Rc<Class>) -> bool {
+        let mut class = Some(self);
+        while let Some(current) = class {
+            if Rc::ptr_eq(current, other) {
+                return true;
+            }
+            class = current.base.as_ref();
+        }
+        false
+    }
+}
+
+impl fmt::Debug for Class {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Class({})", self.name)
+    }
+}
+
+impl PartialEq for Class {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// Module loaded by `import`. Its top-level names stay with the interpreter, keyed by `file`.
+pub struct Module {
+    pub name: String,
+    pub file: String,
+}
+
+impl fmt::Debug for Module {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Module({})", self.name)
+    }
+}
+
+impl PartialEq for Module {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// Function of a built-in module, `math.sqrt`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Native {
+    pub module: &'static str,
+    pub name: &'static str,
+}
+
+// Instance of a class. Objects are shared, every copy refers to the same fields.
+#[derive(Clone)]
+pub struct Object(pub Rc<RefCell<Instance>>);
+
+pub struct Instance {
+    pub class: Rc<Class>,
+    // Fields in the order they were first set
+    pub fields: Vec<(String, Token)>,
+}
+
+impl Object {
+    pub fn new(class: Rc<Class>) -> Self {
+        Object(Rc::new(RefCell::new(Instance {
+            class,
+            fields: Vec::new(),
+        })))
+    }
+
+    pub fn class(&self) -> Rc<Class> {
+        self.0.borrow().class.clone()
+    }
+
+    pub fn field(&self, name: &str) -> Option<Token> {
+        let instance = self.0.borrow();
+        instance
+            .fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.clone())
+    }
+
+    pub fn set_field(&self, name: &str, value: Token) {
+        let mut instance = self.0.borrow_mut();
+        match instance.fields.iter_mut().find(|(field, _)| field == name) {
+            Some((_, field_value)) => *field_value = value,
+            None => instance.fields.push((name.to_string(), value)),
+        }
+    }
+}
+
+impl fmt::Debug for Object {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Object({})", self.class().name)
+    }
+}
+
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
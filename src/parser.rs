@@ -1,20 +1,41 @@
+use crate::bigint::BigInt;
+use crate::error::EzError;
 use crate::tokenizer::*;
 
-#[derive(Debug)]
+// Deepest nesting of parentheses and `not` accepted in one expression
+const MAX_NESTING_DEPTH: usize = 64;
+
+#[derive(Debug, Clone)]
 pub enum ASTNode {
     Integer(i64, usize),
+    BigInteger(BigInt, usize),
     Float(f64, usize),
     Identifier(String, usize),
     Boolean(bool, usize),
     String(String, usize),
+    FormatString(Vec<FormatSegment>, usize),
+    List(Vec<ASTNode>, usize),
     BinaryOperation {
         left: Box<ASTNode>,
         operator: Token,
         right: Box<ASTNode>,
         indent_level: usize,
     },
+    // `a < b < c`, every inner operand is evaluated once
+    ComparisonChain {
+        operands: Vec<ASTNode>,
+        operators: Vec<Token>,
+        indent_level: usize,
+    },
+    // `a = 1`, `a, b = b, a` and `a += 1`, the operator is set for compound assignment
     BindingOperation {
-        variable: Token,
+        targets: Vec<ASTNode>,
+        operator: Option<Token>,
+        value: Box<ASTNode>,
+        indent_level: usize,
+    },
+    UnaryOperation {
+        operator: Token,
         value: Box<ASTNode>,
         indent_level: usize,
     },
@@ -28,6 +49,22 @@ pub enum ASTNode {
         condition: Box<ASTNode>,
         indent_level: usize,
     },
+    // `value if condition else alternative`
+    ConditionalExpression {
+        condition: Box<ASTNode>,
+        value: Box<ASTNode>,
+        alternative: Box<ASTNode>,
+        indent_level: usize,
+    },
+    MatchStatement {
+        subject: Box<ASTNode>,
+        indent_level: usize,
+    },
+    CaseArm {
+        pattern: Pattern,
+        guard: Option<Box<ASTNode>>,
+        indent_level: usize,
+    },
     AlternativeOperation {
         condition: Option<Box<ASTNode>>,
         indent_level: usize,
@@ -36,22 +73,165 @@ pub enum ASTNode {
         value: Box<ASTNode>,
         indent_level: usize,
     },
+    TestDefinition {
+        name: String,
+        indent_level: usize,
+    },
+    IndexOperation {
+        value: Box<ASTNode>,
+        index: Box<ASTNode>,
+        indent_level: usize,
+    },
+    SliceOperation {
+        value: Box<ASTNode>,
+        start: Option<Box<ASTNode>>,
+        end: Option<Box<ASTNode>>,
+        indent_level: usize,
+    },
+    AttributeAccess {
+        object: Box<ASTNode>,
+        name: String,
+        indent_level: usize,
+    },
+    MethodCall {
+        object: Box<ASTNode>,
+        method: String,
+        arguments: Vec<ASTNode>,
+        indent_level: usize,
+    },
+    FunctionCall {
+        name: String,
+        arguments: Vec<ASTNode>,
+        indent_level: usize,
+    },
+    AssertOperation {
+        condition: Box<ASTNode>,
+        message: Option<Box<ASTNode>>,
+        indent_level: usize,
+    },
+    TryStatement {
+        indent_level: usize,
+    },
+    // `except:`, `except ValueError as e:` or `except (ValueError, IndexError):`
+    ExceptArm {
+        kinds: Vec<String>,
+        name: Option<String>,
+        indent_level: usize,
+    },
+    FinallyArm {
+        indent_level: usize,
+    },
+    // A bare `raise` re-raises the error being handled
+    RaiseStatement {
+        value: Option<Box<ASTNode>>,
+        indent_level: usize,
+    },
+    // `def name(a, b):`, the indented lines below are its body
+    FunctionDefinition {
+        name: String,
+        parameters: Vec<String>,
+        indent_level: usize,
+    },
+    // `class Name:` or `class Name(Base):`
+    ClassDefinition {
+        name: String,
+        base: Option<String>,
+        indent_level: usize,
+    },
+    ReturnStatement {
+        value: Option<Box<ASTNode>>,
+        indent_level: usize,
+    },
+    // `import utils` or `import utils as u`
+    ImportStatement {
+        module: String,
+        alias: Option<String>,
+        indent_level: usize,
+    },
+    // `from utils import helper, VERSION as version`, the names with the name they are bound to
+    FromImport {
+        module: String,
+        names: Vec<(String, String)>,
+        indent_level: usize,
+    },
+    // `with open(path) as f:`, the value is closed when the indented block ends
+    WithStatement {
+        value: Box<ASTNode>,
+        name: Option<String>,
+        indent_level: usize,
+    },
+}
+
+// Pattern of a `case` arm
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    // `_`
+    Wildcard,
+    // A name binds the matched value
+    Capture(String),
+    Literal(Box<ASTNode>),
+    // `1..5` excludes the end, `1..=5` includes it
+    Range {
+        start: Box<ASTNode>,
+        end: Box<ASTNode>,
+        inclusive: bool,
+    },
+    // `[first, *rest]`, the rest is a position and an optional name
+    List {
+        elements: Vec<Pattern>,
+        rest: Option<(usize, Option<String>)>,
+    },
+    // `{"name": name, "age": 18..}`, the keys are literals
+    Dictionary(Vec<(Box<ASTNode>, Pattern)>),
+    // `1 | 2 | 3`
+    Alternatives(Vec<Pattern>),
+}
+
+#[derive(Debug, Clone)]
+pub enum FormatSegment {
+    Text(String),
+    Value { value: Box<ASTNode>, spec: String },
 }
 
 impl ASTNode {
     pub fn indent_level(&self) -> usize {
         match self {
             ASTNode::Integer(_, level) => *level,
+            ASTNode::BigInteger(_, level) => *level,
             ASTNode::Float(_, level) => *level,
             ASTNode::Identifier(_, level) => *level,
             ASTNode::Boolean(_, level) => *level,
             ASTNode::String(_, level) => *level,
+            ASTNode::FormatString(_, level) => *level,
+            ASTNode::List(_, level) => *level,
             ASTNode::BinaryOperation { indent_level, .. } => *indent_level,
+            ASTNode::ComparisonChain { indent_level, .. } => *indent_level,
             ASTNode::BindingOperation { indent_level, .. } => *indent_level,
+            ASTNode::UnaryOperation { indent_level, .. } => *indent_level,
             ASTNode::LogicalOperation { indent_level, .. } => *indent_level,
             ASTNode::ConditionalOperation { indent_level, .. } => *indent_level,
+            ASTNode::ConditionalExpression { indent_level, .. } => *indent_level,
+            ASTNode::MatchStatement { indent_level, .. } => *indent_level,
+            ASTNode::CaseArm { indent_level, .. } => *indent_level,
             ASTNode::AlternativeOperation { indent_level, .. } => *indent_level,
             ASTNode::OutputOperation { indent_level, .. } => *indent_level,
+            ASTNode::TestDefinition { indent_level, .. } => *indent_level,
+            ASTNode::IndexOperation { indent_level, .. } => *indent_level,
+            ASTNode::SliceOperation { indent_level, .. } => *indent_level,
+            ASTNode::AttributeAccess { indent_level, .. } => *indent_level,
+            ASTNode::MethodCall { indent_level, .. } => *indent_level,
+            ASTNode::FunctionCall { indent_level, .. } => *indent_level,
+            ASTNode::AssertOperation { indent_level, .. } => *indent_level,
+            ASTNode::TryStatement { indent_level } => *indent_level,
+            ASTNode::ExceptArm { indent_level, .. } => *indent_level,
+            ASTNode::FinallyArm { indent_level } => *indent_level,
+            ASTNode::RaiseStatement { indent_level, .. } => *indent_level,
+            ASTNode::FunctionDefinition { indent_level, .. } => *indent_level,
+            ASTNode::ClassDefinition { indent_level, .. } => *indent_level,
+            ASTNode::ReturnStatement { indent_level, .. } => *indent_level,
+            ASTNode::ImportStatement { indent_level, .. } => *indent_level,
+            ASTNode::FromImport { indent_level, .. } => *indent_level,
+            ASTNode::WithStatement { indent_level, .. } => *indent_level,
         }
     }
 }
@@ -60,232 +240,956 @@ impl ASTNode {
 pub struct Parser<'a> {
     tokenizer: Tokenizer<'a>,
     current_token: Token,
+    depth: usize,
+    line: usize,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(tokenizer: Tokenizer<'a>) -> Self {
-        let mut parser = Parser {
+        Parser {
             tokenizer,
             current_token: Token::EOF,
-        };
-        parser.advance();
-        parser
+            depth: 0,
+            line: 0,
+        }
+    }
+
+    // Line on which the most recently parsed statement starts
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    // Column of the token the parser stopped at
+    pub fn column(&self) -> usize {
+        self.tokenizer.column()
     }
 
-    fn advance(&mut self) {
-        self.current_token = self.tokenizer.next_token();
+    fn advance(&mut self) -> Result<(), EzError> {
+        self.current_token = self.tokenizer.next_token()?;
+        Ok(())
     }
 
-    fn expect(&mut self, expected_token: Token) {
+    fn expect(&mut self, expected_token: Token) -> Result<(), EzError> {
         if self.current_token == expected_token {
-            self.advance();
+            self.advance()
         } else {
-            panic!(
-                "Error p002: Expected token: {:?}, but found: {:?}",
-                expected_token, self.current_token
-            );
+            Err(EzError::new(
+                "p002",
+                format!(
+                    "Expected token: {:?}, but found: {:?}",
+                    expected_token, self.current_token
+                ),
+            ))
         }
     }
 
-    pub fn parse(&mut self) -> ASTNode {
-        // Binding
-        if self.tokenizer.peek_token() == Token::Bind {
-            self.parse_binding()
-        } else {
-            self.parse_condition()
+    // Parses the next statement, None once the whole source has been read
+    pub fn parse(&mut self) -> Result<Option<ASTNode>, EzError> {
+        self.parse_statement()
+            .map_err(|error| error.with_line(self.tokenizer.line))
+    }
+
+    fn parse_statement(&mut self) -> Result<Option<ASTNode>, EzError> {
+        self.advance()?;
+        // Skip blank lines
+        while self.current_token == Token::Newline {
+            self.advance()?;
+        }
+        if self.current_token == Token::EOF {
+            return Ok(None);
+        }
+        self.line = self.tokenizer.line;
+        let node = self.parse_condition()?;
+        // A statement ends with its line
+        match self.current_token {
+            Token::Newline | Token::EOF => Ok(Some(node)),
+            _ => Err(EzError::new(
+                "p001",
+                format!("Unexpected token: {:?}", self.current_token),
+            )),
         }
     }
 
-    fn parse_binding(&mut self) -> ASTNode {
-        let variable = self.current_token.clone();
-        self.advance();
-        self.advance();
-        let node = self.parse_or();
-        ASTNode::BindingOperation {
-            variable,
-            value: Box::new(node),
+    // Assignment or a plain expression statement, the interpreter checks the targets
+    fn parse_binding(&mut self) -> Result<ASTNode, EzError> {
+        let mut targets = self.parse_expression_list()?;
+        let operator = match &self.current_token {
+            Token::Bind => None,
+            Token::CompoundBind(operator) if targets.len() == 1 => Some(*operator.clone()),
+            _ if targets.len() == 1 => return Ok(targets.remove(0)),
+            _ => return Ok(ASTNode::List(targets, self.tokenizer.indent_level)),
+        };
+        self.advance()?;
+        let mut values = self.parse_expression_list()?;
+        // `a = 1, 2` binds a list
+        let value = if values.len() == 1 {
+            values.remove(0)
+        } else {
+            ASTNode::List(values, self.tokenizer.indent_level)
+        };
+        Ok(ASTNode::BindingOperation {
+            targets,
+            operator,
+            value: Box::new(value),
             indent_level: self.tokenizer.indent_level,
+        })
+    }
+
+    // One or more comma separated expressions
+    fn parse_expression_list(&mut self) -> Result<Vec<ASTNode>, EzError> {
+        let mut expressions = vec![self.parse_expression()?];
+        while self.current_token == Token::Comma {
+            self.advance()?;
+            expressions.push(self.parse_expression()?);
         }
+        Ok(expressions)
     }
 
-    fn parse_condition(&mut self) -> ASTNode {
+    fn parse_condition(&mut self) -> Result<ASTNode, EzError> {
         match self.current_token {
             // Conditional
             Token::If => {
-                self.advance();
-                let node = self.parse_or();
-                self.expect(Token::Colon);
-                ASTNode::ConditionalOperation {
+                self.advance()?;
+                let node = self.parse_expression()?;
+                self.expect(Token::Colon)?;
+                Ok(ASTNode::ConditionalOperation {
                     condition: Box::new(node),
                     indent_level: self.tokenizer.indent_level,
-                }
+                })
+            }
+            Token::Else => {
+                self.advance()?;
+                self.expect(Token::Colon)?;
+                Ok(ASTNode::AlternativeOperation {
+                    condition: None,
+                    indent_level: self.tokenizer.indent_level,
+                })
             }
-            Token::Else => ASTNode::AlternativeOperation {
-                condition: None,
-                indent_level: self.tokenizer.indent_level,
-            },
             Token::ElseIf => {
-                self.advance();
-                let node = self.parse_or();
-                self.expect(Token::Colon);
-                ASTNode::AlternativeOperation {
+                self.advance()?;
+                let node = self.parse_expression()?;
+                self.expect(Token::Colon)?;
+                Ok(ASTNode::AlternativeOperation {
                     condition: Some(Box::new(node)),
                     indent_level: self.tokenizer.indent_level,
+                })
+            }
+            Token::Def => {
+                self.advance()?;
+                let name = self.parse_name()?;
+                self.expect(Token::OpenParen)?;
+                let mut parameters = Vec::new();
+                while self.current_token != Token::CloseParen {
+                    let parameter = self.parse_name()?;
+                    if parameters.contains(&parameter) {
+                        return Err(EzError::new(
+                            "p001",
+                            format!("Duplicate parameter: {}", parameter),
+                        ));
+                    }
+                    parameters.push(parameter);
+                    if self.current_token != Token::Comma {
+                        break;
+                    }
+                    self.advance()?;
+                }
+                self.expect(Token::CloseParen)?;
+                self.expect(Token::Colon)?;
+                Ok(ASTNode::FunctionDefinition {
+                    name,
+                    parameters,
+                    indent_level: self.tokenizer.indent_level,
+                })
+            }
+            Token::Class => {
+                self.advance()?;
+                let name = self.parse_name()?;
+                let mut base = None;
+                if self.current_token == Token::OpenParen {
+                    self.advance()?;
+                    base = Some(self.parse_name()?);
+                    self.expect(Token::CloseParen)?;
+                }
+                self.expect(Token::Colon)?;
+                Ok(ASTNode::ClassDefinition {
+                    name,
+                    base,
+                    indent_level: self.tokenizer.indent_level,
+                })
+            }
+            Token::Try => {
+                self.advance()?;
+                self.expect(Token::Colon)?;
+                Ok(ASTNode::TryStatement {
+                    indent_level: self.tokenizer.indent_level,
+                })
+            }
+            Token::Except => {
+                self.advance()?;
+                let mut kinds = Vec::new();
+                if self.current_token == Token::OpenParen {
+                    self.advance()?;
+                    kinds.push(self.parse_name()?);
+                    while self.current_token == Token::Comma {
+                        self.advance()?;
+                        kinds.push(self.parse_name()?);
+                    }
+                    self.expect(Token::CloseParen)?;
+                } else if self.current_token != Token::Colon {
+                    kinds.push(self.parse_name()?);
+                }
+                let mut name = None;
+                if self.current_token == Token::As {
+                    self.advance()?;
+                    name = Some(self.parse_name()?);
                 }
+                self.expect(Token::Colon)?;
+                Ok(ASTNode::ExceptArm {
+                    kinds,
+                    name,
+                    indent_level: self.tokenizer.indent_level,
+                })
+            }
+            Token::Finally => {
+                self.advance()?;
+                self.expect(Token::Colon)?;
+                Ok(ASTNode::FinallyArm {
+                    indent_level: self.tokenizer.indent_level,
+                })
+            }
+            // `match` and `case` are only keywords on lines that open a block
+            Token::Identifier(ref keyword) if keyword == "match" && self.line_ends_with_colon() => {
+                self.advance()?;
+                let subject = self.parse_expression()?;
+                self.expect(Token::Colon)?;
+                Ok(ASTNode::MatchStatement {
+                    subject: Box::new(subject),
+                    indent_level: self.tokenizer.indent_level,
+                })
+            }
+            Token::Identifier(ref keyword) if keyword == "case" && self.line_ends_with_colon() => {
+                self.advance()?;
+                let pattern = self.parse_pattern()?;
+                let mut guard = None;
+                if self.current_token == Token::If {
+                    self.advance()?;
+                    guard = Some(Box::new(self.parse_expression()?));
+                }
+                self.expect(Token::Colon)?;
+                Ok(ASTNode::CaseArm {
+                    pattern,
+                    guard,
+                    indent_level: self.tokenizer.indent_level,
+                })
+            }
+            // Test block, "test" is only a keyword when followed by a name
+            Token::Identifier(ref keyword)
+                if keyword == "test" && matches!(self.tokenizer.peek_token()?, Token::String(_)) =>
+            {
+                self.advance()?;
+                let name = match &self.current_token {
+                    Token::String(value) => value.clone(),
+                    _ => unreachable!(),
+                };
+                self.advance()?;
+                self.expect(Token::Colon)?;
+                Ok(ASTNode::TestDefinition {
+                    name,
+                    indent_level: self.tokenizer.indent_level,
+                })
             }
             _ => self.parse_print(),
         }
     }
 
-    fn parse_print(&mut self) -> ASTNode {
+    fn parse_print(&mut self) -> Result<ASTNode, EzError> {
         match self.current_token {
             //Print
             Token::Print => {
-                self.advance();
-                self.expect(Token::OpenParen);
-                let node = self.parse_or();
-                self.expect(Token::CloseParen);
-                ASTNode::OutputOperation {
+                self.advance()?;
+                self.expect(Token::OpenParen)?;
+                let node = self.parse_expression()?;
+                self.expect(Token::CloseParen)?;
+                Ok(ASTNode::OutputOperation {
                     value: Box::new(node),
                     indent_level: self.tokenizer.indent_level,
+                })
+            }
+            _ => self.parse_assert(),
+        }
+    }
+
+    fn parse_assert(&mut self) -> Result<ASTNode, EzError> {
+        match self.current_token {
+            // Assert
+            Token::Assert => {
+                self.advance()?;
+                let condition = self.parse_expression()?;
+                let mut message = None;
+                if self.current_token == Token::Comma {
+                    self.advance()?;
+                    message = Some(Box::new(self.parse_expression()?));
+                }
+                Ok(ASTNode::AssertOperation {
+                    condition: Box::new(condition),
+                    message,
+                    indent_level: self.tokenizer.indent_level,
+                })
+            }
+            _ => self.parse_raise(),
+        }
+    }
+
+    fn parse_raise(&mut self) -> Result<ASTNode, EzError> {
+        match self.current_token {
+            Token::Raise => {
+                self.advance()?;
+                let value = match self.current_token {
+                    Token::Newline | Token::EOF => None,
+                    _ => Some(Box::new(self.parse_expression()?)),
+                };
+                Ok(ASTNode::RaiseStatement {
+                    value,
+                    indent_level: self.tokenizer.indent_level,
+                })
+            }
+            Token::Return => {
+                self.advance()?;
+                // `return a, b` returns a list
+                let value = match self.current_token {
+                    Token::Newline | Token::EOF => None,
+                    _ => {
+                        let mut values = self.parse_expression_list()?;
+                        Some(Box::new(if values.len() == 1 {
+                            values.remove(0)
+                        } else {
+                            ASTNode::List(values, self.tokenizer.indent_level)
+                        }))
+                    }
+                };
+                Ok(ASTNode::ReturnStatement {
+                    value,
+                    indent_level: self.tokenizer.indent_level,
+                })
+            }
+            Token::Import => {
+                self.advance()?;
+                let module = self.parse_name()?;
+                let alias = self.parse_alias()?;
+                Ok(ASTNode::ImportStatement {
+                    module,
+                    alias,
+                    indent_level: self.tokenizer.indent_level,
+                })
+            }
+            Token::From => {
+                self.advance()?;
+                let module = self.parse_name()?;
+                self.expect(Token::Import)?;
+                let mut names = Vec::new();
+                loop {
+                    let name = self.parse_name()?;
+                    let alias = self.parse_alias()?.unwrap_or_else(|| name.clone());
+                    names.push((name, alias));
+                    if self.current_token != Token::Comma {
+                        break;
+                    }
+                    self.advance()?;
+                }
+                Ok(ASTNode::FromImport {
+                    module,
+                    names,
+                    indent_level: self.tokenizer.indent_level,
+                })
+            }
+            Token::With => {
+                self.advance()?;
+                let value = self.parse_expression()?;
+                let name = self.parse_alias()?;
+                self.expect(Token::Colon)?;
+                Ok(ASTNode::WithStatement {
+                    value: Box::new(value),
+                    name,
+                    indent_level: self.tokenizer.indent_level,
+                })
+            }
+            _ => self.parse_binding(),
+        }
+    }
+
+    fn parse_name(&mut self) -> Result<String, EzError> {
+        match &self.current_token {
+            Token::Identifier(name) => {
+                let name = name.clone();
+                self.advance()?;
+                Ok(name)
+            }
+            _ => Err(EzError::new(
+                "p002",
+                format!(
+                    "Expected token: Identifier, but found: {:?}",
+                    self.current_token
+                ),
+            )),
+        }
+    }
+
+    // `as name` after an imported name or a `with` value
+    fn parse_alias(&mut self) -> Result<Option<String>, EzError> {
+        if self.current_token != Token::As {
+            return Ok(None);
+        }
+        self.advance()?;
+        self.parse_name().map(Some)
+    }
+
+    fn line_ends_with_colon(&self) -> bool {
+        let mut lookahead = self.tokenizer.clone();
+        let mut last = self.current_token.clone();
+        loop {
+            match lookahead.next_token() {
+                Ok(Token::Newline | Token::EOF) => return last == Token::Colon,
+                Ok(token) => last = token,
+                Err(_) => return false,
+            }
+        }
+    }
+
+    // Alternatives separated by `|`
+    fn parse_pattern(&mut self) -> Result<Pattern, EzError> {
+        let mut alternatives = vec![self.parse_single_pattern()?];
+        while self.current_token == Token::Pipe {
+            self.advance()?;
+            alternatives.push(self.parse_single_pattern()?);
+        }
+        if alternatives.len() == 1 {
+            Ok(alternatives.remove(0))
+        } else {
+            Ok(Pattern::Alternatives(alternatives))
+        }
+    }
+
+    fn parse_single_pattern(&mut self) -> Result<Pattern, EzError> {
+        match &self.current_token {
+            Token::Identifier(name) => {
+                let pattern = if name == "_" {
+                    Pattern::Wildcard
+                } else {
+                    Pattern::Capture(name.clone())
+                };
+                self.advance()?;
+                Ok(pattern)
+            }
+            Token::OpenBracket => {
+                self.advance()?;
+                self.enter_nesting()?;
+                let mut elements = Vec::new();
+                let mut rest = None;
+                while self.current_token != Token::CloseBracket {
+                    if self.current_token == Token::Asterisk && rest.is_none() {
+                        self.advance()?;
+                        let name = match &self.current_token {
+                            Token::Identifier(name) if name == "_" => None,
+                            Token::Identifier(name) => Some(name.clone()),
+                            _ => {
+                                return Err(EzError::new(
+                                    "p001",
+                                    format!("Unexpected token: {:?}", self.current_token),
+                                ))
+                            }
+                        };
+                        self.advance()?;
+                        rest = Some((elements.len(), name));
+                    } else {
+                        elements.push(self.parse_pattern()?);
+                    }
+                    if self.current_token == Token::Comma {
+                        self.advance()?;
+                    } else {
+                        break;
+                    }
+                }
+                self.depth -= 1;
+                self.expect(Token::CloseBracket)?;
+                Ok(Pattern::List { elements, rest })
+            }
+            Token::OpenBrace => {
+                self.advance()?;
+                self.enter_nesting()?;
+                let mut entries = Vec::new();
+                while self.current_token != Token::CloseBrace {
+                    let key = match self.parse_single_pattern()? {
+                        Pattern::Literal(key) => key,
+                        _ => {
+                            return Err(EzError::new(
+                                "p001",
+                                String::from("Dictionary pattern keys must be literals"),
+                            ))
+                        }
+                    };
+                    self.expect(Token::Colon)?;
+                    entries.push((key, self.parse_pattern()?));
+                    if self.current_token == Token::Comma {
+                        self.advance()?;
+                    } else {
+                        break;
+                    }
                 }
+                self.depth -= 1;
+                self.expect(Token::CloseBrace)?;
+                Ok(Pattern::Dictionary(entries))
+            }
+            Token::Integer(_)
+            | Token::BigInteger(_)
+            | Token::Float(_)
+            | Token::String(_)
+            | Token::Boolean(_)
+            | Token::Minus => {
+                let start = self.parse_unary()?;
+                let inclusive = match self.current_token {
+                    Token::DotDot => false,
+                    Token::DotDotEqual => true,
+                    _ => return Ok(Pattern::Literal(Box::new(start))),
+                };
+                self.advance()?;
+                Ok(Pattern::Range {
+                    start: Box::new(start),
+                    end: Box::new(self.parse_unary()?),
+                    inclusive,
+                })
+            }
+            _ => Err(EzError::new(
+                "p001",
+                format!("Unexpected token: {:?}", self.current_token),
+            )),
+        }
+    }
+
+    // Lowest precedence, `value if condition else alternative`
+    fn parse_expression(&mut self) -> Result<ASTNode, EzError> {
+        let value = self.parse_or()?;
+
+        if self.current_token != Token::If {
+            return Ok(value);
+        }
+        self.advance()?;
+        let condition = self.parse_or()?;
+        self.expect(Token::Else)?;
+        self.enter_nesting()?;
+        let alternative = self.parse_expression()?;
+        self.depth -= 1;
+        Ok(ASTNode::ConditionalExpression {
+            condition: Box::new(condition),
+            value: Box::new(value),
+            alternative: Box::new(alternative),
+            indent_level: self.tokenizer.indent_level,
+        })
+    }
+
+    fn parse_or(&mut self) -> Result<ASTNode, EzError> {
+        let mut node = self.parse_and()?;
+
+        while self.current_token == Token::Or {
+            let operator = self.current_token.clone();
+            self.advance()?;
+            node = ASTNode::LogicalOperation {
+                left: Box::new(node),
+                operator,
+                right: Box::new(self.parse_and()?),
+                indent_level: self.tokenizer.indent_level,
+            };
+        }
+        Ok(node)
+    }
+
+    fn parse_and(&mut self) -> Result<ASTNode, EzError> {
+        let mut node = self.parse_comparison()?;
+
+        while self.current_token == Token::And {
+            let operator = self.current_token.clone();
+            self.advance()?;
+            node = ASTNode::LogicalOperation {
+                left: Box::new(node),
+                operator,
+                right: Box::new(self.parse_comparison()?),
+                indent_level: self.tokenizer.indent_level,
+            };
+        }
+        Ok(node)
+    }
+
+    fn parse_comparison(&mut self) -> Result<ASTNode, EzError> {
+        let mut operands = vec![self.parse_bitwise_or()?];
+        let mut operators = Vec::new();
+
+        while matches!(self.current_token, Token::Comparison(_)) {
+            operators.push(self.current_token.clone());
+            self.advance()?;
+            operands.push(self.parse_bitwise_or()?);
+        }
+        match operators.len() {
+            0 => Ok(operands.remove(0)),
+            1 => {
+                let right = operands.remove(1);
+                Ok(ASTNode::BinaryOperation {
+                    left: Box::new(operands.remove(0)),
+                    operator: operators.remove(0),
+                    right: Box::new(right),
+                    indent_level: self.tokenizer.indent_level,
+                })
             }
-            _ => self.parse_or(),
+            _ => Ok(ASTNode::ComparisonChain {
+                operands,
+                operators,
+                indent_level: self.tokenizer.indent_level,
+            }),
         }
     }
 
-    fn parse_or(&mut self) -> ASTNode {
-        let mut node = self.parse_and();
+    fn parse_bitwise_or(&mut self) -> Result<ASTNode, EzError> {
+        let mut node = self.parse_bitwise_xor()?;
 
-        while self.current_token == Token::Or{
+        while self.current_token == Token::Pipe {
             let operator = self.current_token.clone();
-            self.advance();
+            self.advance()?;
             node = ASTNode::BinaryOperation {
                 left: Box::new(node),
                 operator,
-                right: Box::new(self.parse_and()),
+                right: Box::new(self.parse_bitwise_xor()?),
                 indent_level: self.tokenizer.indent_level,
             };
         }
-        node
+        Ok(node)
     }
 
-    fn parse_and(&mut self) -> ASTNode {
-        let mut node = self.parse_comparison();
+    fn parse_bitwise_xor(&mut self) -> Result<ASTNode, EzError> {
+        let mut node = self.parse_bitwise_and()?;
 
-        while self.current_token == Token::And{
+        while self.current_token == Token::Caret {
             let operator = self.current_token.clone();
-            self.advance();
+            self.advance()?;
             node = ASTNode::BinaryOperation {
                 left: Box::new(node),
                 operator,
-                right: Box::new(self.parse_comparison()),
+                right: Box::new(self.parse_bitwise_and()?),
                 indent_level: self.tokenizer.indent_level,
             };
         }
-        node
+        Ok(node)
     }
 
-    fn parse_comparison(&mut self) -> ASTNode {
-        let mut node = self.parse_addition();
+    fn parse_bitwise_and(&mut self) -> Result<ASTNode, EzError> {
+        let mut node = self.parse_shift()?;
 
-        while self.current_token == Token::Comparison(Compare::Equal)
-        || self.current_token == Token::Comparison(Compare::NotEqual)
-        || self.current_token == Token::Comparison(Compare::GreaterThan)
-        || self.current_token == Token::Comparison(Compare::LessThan)
-        || self.current_token == Token::Comparison(Compare::GreaterThanOrEqual)
-        || self.current_token == Token::Comparison(Compare::LessThanOrEqual){
+        while self.current_token == Token::Ampersand {
             let operator = self.current_token.clone();
-            self.advance();
+            self.advance()?;
             node = ASTNode::BinaryOperation {
                 left: Box::new(node),
                 operator,
-                right: Box::new(self.parse_addition()),
+                right: Box::new(self.parse_shift()?),
                 indent_level: self.tokenizer.indent_level,
             };
         }
-        node
+        Ok(node)
     }
 
-    fn parse_addition(&mut self) -> ASTNode {
-        let mut node = self.parse_multiplication();
+    fn parse_shift(&mut self) -> Result<ASTNode, EzError> {
+        let mut node = self.parse_addition()?;
+
+        while self.current_token == Token::ShiftLeft || self.current_token == Token::ShiftRight {
+            let operator = self.current_token.clone();
+            self.advance()?;
+            node = ASTNode::BinaryOperation {
+                left: Box::new(node),
+                operator,
+                right: Box::new(self.parse_addition()?),
+                indent_level: self.tokenizer.indent_level,
+            };
+        }
+        Ok(node)
+    }
+
+    fn parse_addition(&mut self) -> Result<ASTNode, EzError> {
+        let mut node = self.parse_multiplication()?;
 
         while self.current_token == Token::Plus || self.current_token == Token::Minus {
             let operator = self.current_token.clone();
-            self.advance();
+            self.advance()?;
             node = ASTNode::BinaryOperation {
                 left: Box::new(node),
                 operator,
-                right: Box::new(self.parse_multiplication()),
+                right: Box::new(self.parse_multiplication()?),
                 indent_level: self.tokenizer.indent_level,
             };
         }
-        node
+        Ok(node)
     }
 
-    fn parse_multiplication(&mut self) -> ASTNode {
-        let mut node = self.parse_primary();
+    fn parse_multiplication(&mut self) -> Result<ASTNode, EzError> {
+        let mut node = self.parse_unary()?;
 
         while self.current_token == Token::Asterisk
             || self.current_token == Token::Slash
+            || self.current_token == Token::DoubleSlash
             || self.current_token == Token::Modulo
         {
             let operator = self.current_token.clone();
-            self.advance();
+            self.advance()?;
             node = ASTNode::BinaryOperation {
                 left: Box::new(node),
                 operator,
-                right: Box::new(self.parse_primary()),
+                right: Box::new(self.parse_unary()?),
                 indent_level: self.tokenizer.indent_level,
             };
         }
-        node
+        Ok(node)
+    }
+
+    fn parse_unary(&mut self) -> Result<ASTNode, EzError> {
+        match self.current_token {
+            Token::Minus | Token::Plus | Token::Tilde => {
+                let operator = self.current_token.clone();
+                self.advance()?;
+                self.enter_nesting()?;
+                let node = self.parse_unary()?;
+                self.depth -= 1;
+                Ok(ASTNode::UnaryOperation {
+                    operator,
+                    value: Box::new(node),
+                    indent_level: self.tokenizer.indent_level,
+                })
+            }
+            _ => self.parse_power(),
+        }
+    }
+
+    // Right-associative and binds tighter than a unary operator on its left, -2 ** 2 is -4
+    fn parse_power(&mut self) -> Result<ASTNode, EzError> {
+        let node = self.parse_postfix()?;
+
+        if self.current_token == Token::DoubleAsterisk {
+            let operator = self.current_token.clone();
+            self.advance()?;
+            self.enter_nesting()?;
+            let exponent = self.parse_unary()?;
+            self.depth -= 1;
+            return Ok(ASTNode::BinaryOperation {
+                left: Box::new(node),
+                operator,
+                right: Box::new(exponent),
+                indent_level: self.tokenizer.indent_level,
+            });
+        }
+        Ok(node)
     }
 
-    fn parse_primary(&mut self) -> ASTNode {
+    fn parse_postfix(&mut self) -> Result<ASTNode, EzError> {
+        let mut node = self.parse_primary()?;
+
+        loop {
+            match self.current_token {
+                // Attribute or method call
+                Token::Dot => {
+                    self.advance()?;
+                    let method = match &self.current_token {
+                        Token::Identifier(name) => name.clone(),
+                        _ => {
+                            return Err(EzError::new(
+                                "p001",
+                                format!("Unexpected token: {:?}", self.current_token),
+                            ))
+                        }
+                    };
+                    self.advance()?;
+                    if self.current_token != Token::OpenParen {
+                        node = ASTNode::AttributeAccess {
+                            object: Box::new(node),
+                            name: method,
+                            indent_level: self.tokenizer.indent_level,
+                        };
+                        continue;
+                    }
+                    self.advance()?;
+                    let arguments = self.parse_arguments(Token::CloseParen)?;
+                    node = ASTNode::MethodCall {
+                        object: Box::new(node),
+                        method,
+                        arguments,
+                        indent_level: self.tokenizer.indent_level,
+                    };
+                }
+                // Index or slice
+                Token::OpenBracket => {
+                    self.advance()?;
+                    self.enter_nesting()?;
+                    let start = if self.current_token == Token::Colon {
+                        None
+                    } else {
+                        Some(Box::new(self.parse_expression()?))
+                    };
+                    if self.current_token == Token::Colon {
+                        self.advance()?;
+                        let end = if self.current_token == Token::CloseBracket {
+                            None
+                        } else {
+                            Some(Box::new(self.parse_expression()?))
+                        };
+                        node = ASTNode::SliceOperation {
+                            value: Box::new(node),
+                            start,
+                            end,
+                            indent_level: self.tokenizer.indent_level,
+                        };
+                    } else {
+                        node = ASTNode::IndexOperation {
+                            value: Box::new(node),
+                            // Only a slice may leave out the start
+                            index: start.ok_or_else(|| {
+                                EzError::new("p001", String::from("Unexpected token: CloseBracket"))
+                            })?,
+                            indent_level: self.tokenizer.indent_level,
+                        };
+                    }
+                    self.depth -= 1;
+                    self.expect(Token::CloseBracket)?;
+                }
+                _ => break,
+            }
+        }
+        Ok(node)
+    }
+
+    // Expression embedded in an f-string, parsed on its own but sharing the nesting limit
+    fn parse_field(&mut self, source: &str) -> Result<ASTNode, EzError> {
+        let mut parser = Parser::new(Tokenizer::new(source));
+        parser.depth = self.depth;
+        parser.enter_nesting()?;
+        parser.advance()?;
+        let node = parser.parse_expression()?;
+        if parser.current_token != Token::EOF {
+            return Err(EzError::new(
+                "p001",
+                format!("Unexpected token: {:?}", parser.current_token),
+            ));
+        }
+        Ok(node)
+    }
+
+    // Comma separated expressions up to the closing token, which is consumed
+    fn parse_arguments(&mut self, closing: Token) -> Result<Vec<ASTNode>, EzError> {
+        self.enter_nesting()?;
+        let mut arguments = Vec::new();
+        while self.current_token != closing {
+            arguments.push(self.parse_expression()?);
+            if self.current_token == Token::Comma {
+                self.advance()?;
+            } else {
+                break;
+            }
+        }
+        self.depth -= 1;
+        self.expect(closing)?;
+        Ok(arguments)
+    }
+
+    fn parse_primary(&mut self) -> Result<ASTNode, EzError> {
         match &self.current_token {
             Token::Integer(value) => {
+                let number = *value;
+                self.advance()?;
+                Ok(ASTNode::Integer(number, self.tokenizer.indent_level))
+            }
+            Token::BigInteger(value) => {
                 let number = value.clone();
-                self.advance();
-                ASTNode::Integer(number, self.tokenizer.indent_level)
+                self.advance()?;
+                Ok(ASTNode::BigInteger(number, self.tokenizer.indent_level))
             }
             Token::Float(value) => {
-                let number = value.clone();
-                self.advance();
-                ASTNode::Float(number, self.tokenizer.indent_level)
+                let number = *value;
+                self.advance()?;
+                Ok(ASTNode::Float(number, self.tokenizer.indent_level))
             }
             Token::Identifier(name) => {
                 let identifier = name.clone();
-                self.advance();
-                ASTNode::Identifier(identifier, self.tokenizer.indent_level)
+                self.advance()?;
+                // Function call
+                if self.current_token == Token::OpenParen {
+                    self.advance()?;
+                    let arguments = self.parse_arguments(Token::CloseParen)?;
+                    return Ok(ASTNode::FunctionCall {
+                        name: identifier,
+                        arguments,
+                        indent_level: self.tokenizer.indent_level,
+                    });
+                }
+                Ok(ASTNode::Identifier(identifier, self.tokenizer.indent_level))
+            }
+            Token::FormatString(parts) => {
+                let parts = parts.clone();
+                self.advance()?;
+                let mut segments = Vec::with_capacity(parts.len());
+                for part in parts {
+                    segments.push(match part {
+                        FormatPart::Text(text) => FormatSegment::Text(text),
+                        FormatPart::Field { expression, spec } => FormatSegment::Value {
+                            value: Box::new(self.parse_field(&expression)?),
+                            spec,
+                        },
+                    });
+                }
+                Ok(ASTNode::FormatString(segments, self.tokenizer.indent_level))
+            }
+            Token::OpenBracket => {
+                self.advance()?;
+                let elements = self.parse_arguments(Token::CloseBracket)?;
+                Ok(ASTNode::List(elements, self.tokenizer.indent_level))
             }
             Token::OpenParen => {
-                self.advance();
-                let node = self.parse_or();
-                self.expect(Token::CloseParen);
-                node
+                self.advance()?;
+                self.enter_nesting()?;
+                let node = self.parse_expression()?;
+                self.depth -= 1;
+                self.expect(Token::CloseParen)?;
+                Ok(node)
             }
             Token::Boolean(value) => {
-                let bool = value.clone();
-                self.advance();
-                ASTNode::Boolean(bool, self.tokenizer.indent_level)
+                let bool = *value;
+                self.advance()?;
+                Ok(ASTNode::Boolean(bool, self.tokenizer.indent_level))
             }
             Token::Not => {
-                self.advance();
-                let node = self.parse_primary();
-                ASTNode::LogicalOperation {
+                self.advance()?;
+                self.enter_nesting()?;
+                let node = self.parse_unary()?;
+                self.depth -= 1;
+                Ok(ASTNode::LogicalOperation {
                     left: Box::new(ASTNode::Boolean(false, self.tokenizer.indent_level)),
                     operator: Token::Not,
                     right: Box::new(node),
                     indent_level: self.tokenizer.indent_level,
-                }
-            } 
+                })
+            }
             Token::String(value) => {
                 let string = value.clone();
-                self.advance();
-                ASTNode::String(string, self.tokenizer.indent_level)
+                self.advance()?;
+                Ok(ASTNode::String(string, self.tokenizer.indent_level))
             }
-            _ => panic!("Error p001: Unexpected token: {:?}", self.current_token),
+            _ => Err(EzError::new(
+                "p001",
+                format!("Unexpected token: {:?}", self.current_token),
+            )),
+        }
+    }
+
+    fn enter_nesting(&mut self) -> Result<(), EzError> {
+        self.depth += 1;
+        if self.depth > MAX_NESTING_DEPTH {
+            return Err(EzError::new(
+                "p003",
+                String::from("Expression is nested too deeply"),
+            ));
         }
+        Ok(())
     }
 }
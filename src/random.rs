@@ -0,0 +1,133 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{argument_count, expected, EzError};
+use crate::tokenizer::Token;
+
+// Functions of the built-in `random` module
+pub const FUNCTIONS: &[&str] = &["int", "float", "choice", "shuffle", "seed"];
+
+// SplitMix64, small and fast with good statistical quality. The same seed gives
+// the same numbers on every platform.
+#[derive(Debug)]
+pub struct Random {
+    state: u64,
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Random {
+    // Seeded from the clock, `seed` makes runs reproducible
+    pub fn new() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        Random {
+            state: nanos ^ u64::from(std::process::id()).rotate_left(32),
+        }
+    }
+
+    pub fn seed(&mut self, seed: u64) {
+        self.state = seed;
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut mixed = self.state;
+        mixed = (mixed ^ (mixed >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        mixed = (mixed ^ (mixed >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        mixed ^ (mixed >> 31)
+    }
+
+    // Uniform in 0..bound, rejecting the values that would favour small results
+    fn below(&mut self, bound: u64) -> u64 {
+        let limit = u64::MAX - u64::MAX % bound;
+        loop {
+            let value = self.next();
+            if value < limit {
+                return value % bound;
+            }
+        }
+    }
+
+    // Uniform in [0, 1) with the 53 bits a float can hold
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn call(&mut self, function: &str, arguments: &[Token]) -> Result<Token, EzError> {
+        match (function, arguments) {
+            // Both bounds are included, random.int(1, 6) is a die
+            ("int", [Token::Integer(low), Token::Integer(high)]) => {
+                if low > high {
+                    return Err(EzError::new(
+                        "i018",
+                        format!("Empty range for random.int: {} > {}", low, high),
+                    ));
+                }
+                let span = high.abs_diff(*low);
+                let offset = match span.checked_add(1) {
+                    Some(bound) => self.below(bound),
+                    None => self.next(),
+                };
+                Ok(Token::Integer(low.wrapping_add(offset as i64)))
+            }
+            ("int", [Token::Integer(_), value] | [value, _]) => {
+                Err(expected(function, "integers", value))
+            }
+            ("float", []) => Ok(Token::Float(self.unit())),
+            ("choice", [Token::List(values)]) => match values.len() {
+                0 => Err(empty_sequence()),
+                length => Ok(values[self.below(length as u64) as usize].clone()),
+            },
+            ("choice", [Token::String(text)]) => {
+                let characters: Vec<char> = text.chars().collect();
+                match characters.len() {
+                    0 => Err(empty_sequence()),
+                    length => Ok(Token::String(
+                        characters[self.below(length as u64) as usize].to_string(),
+                    )),
+                }
+            }
+            // Lists are values, the shuffled list is returned
+            ("shuffle", [Token::List(values)]) => {
+                let mut values = values.clone();
+                // Fisher-Yates
+                for index in (1..values.len()).rev() {
+                    let other = self.below(index as u64 + 1) as usize;
+                    values.swap(index, other);
+                }
+                Ok(Token::List(values))
+            }
+            ("seed", [Token::Integer(seed)]) => {
+                self.seed(*seed as u64);
+                Ok(Token::None)
+            }
+            ("choice" | "shuffle" | "seed", [value]) => Err(expected(
+                function,
+                if function == "seed" { "an integer" } else { "a list" },
+                value,
+            )),
+            ("int" | "float" | "choice" | "shuffle" | "seed", _) => Err(argument_count(
+                function,
+                match function {
+                    "int" => "2",
+                    "float" => "0",
+                    _ => "1",
+                },
+                arguments,
+            )),
+            _ => Err(EzError::new(
+                "i016",
+                format!("Module random has no attribute: {}", function),
+            )),
+        }
+    }
+}
+
+fn empty_sequence() -> EzError {
+    EzError::new("i015", String::from("Cannot choose from an empty sequence"))
+}
@@ -0,0 +1,375 @@
+use crate::error::EzError;
+use crate::interpreter::{display, type_names};
+use crate::tokenizer::Token;
+
+// Widest field and highest precision a format spec may ask for
+const MAX_FORMAT_WIDTH: usize = 1 << 16;
+
+// Built-in string methods. Positions are counted in characters, not bytes.
+pub fn call_method(value: &str, method: &str, arguments: Vec<Token>) -> Result<Token, EzError> {
+    let token = match (method, arguments.as_slice()) {
+        ("upper", []) => Token::String(value.to_uppercase()),
+        ("lower", []) => Token::String(value.to_lowercase()),
+        ("strip", []) => Token::String(value.trim().to_string()),
+        ("strip", [Token::String(chars)]) => {
+            Token::String(value.trim_matches(|c| chars.contains(c)).to_string())
+        }
+        ("split", []) => Token::List(
+            value
+                .split_whitespace()
+                .map(|part| Token::String(part.to_string()))
+                .collect(),
+        ),
+        ("split", [Token::String(separator)]) => {
+            if separator.is_empty() {
+                return Err(EzError::new("i018", String::from("Empty separator")));
+            }
+            Token::List(
+                value
+                    .split(separator.as_str())
+                    .map(|part| Token::String(part.to_string()))
+                    .collect(),
+            )
+        }
+        ("join", [Token::List(items)]) => {
+            let mut parts = Vec::new();
+            for item in items {
+                match item {
+                    Token::String(part) => parts.push(part.as_str()),
+                    _ => return Err(wrong_arguments(method, &arguments)),
+                }
+            }
+            Token::String(parts.join(value))
+        }
+        ("replace", [Token::String(old), Token::String(new)]) => {
+            Token::String(value.replace(old.as_str(), new))
+        }
+        ("find", [Token::String(needle)]) => match value.find(needle.as_str()) {
+            Some(byte_index) => Token::Integer(value[..byte_index].chars().count() as i64),
+            None => Token::Integer(-1),
+        },
+        ("startswith", [Token::String(prefix)]) => {
+            Token::Boolean(value.starts_with(prefix.as_str()))
+        }
+        ("endswith", [Token::String(suffix)]) => Token::Boolean(value.ends_with(suffix.as_str())),
+        ("len", []) => Token::Integer(value.chars().count() as i64),
+        ("format", _) => Token::String(format(value, &arguments)?),
+        (
+            "upper" | "lower" | "strip" | "split" | "join" | "replace" | "find" | "startswith"
+            | "endswith" | "len",
+            _,
+        ) => return Err(wrong_arguments(method, &arguments)),
+        _ => {
+            return Err(EzError::new(
+                "i016",
+                format!("String has no method: {}", method),
+            ))
+        }
+    };
+    Ok(token)
+}
+
+// Replaces {} with the next argument and {n} with the n-th one, {{ and }} are literal braces.
+// Both may be followed by a format spec, `{:>5}` or `{0:.2f}`.
+fn format(template: &str, arguments: &[Token]) -> Result<String, EzError> {
+    let mut result = String::new();
+    let mut next_argument = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                result.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                result.push('}');
+            }
+            '{' => {
+                let mut field = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => field.push(c),
+                        None => {
+                            return Err(EzError::new(
+                                "i018",
+                                String::from("Unclosed '{' in format string"),
+                            ))
+                        }
+                    }
+                }
+                let (field, spec) = field.split_once(':').unwrap_or((&field, ""));
+                let index = if field.is_empty() {
+                    next_argument += 1;
+                    next_argument - 1
+                } else {
+                    field.parse::<usize>().map_err(|_| {
+                        EzError::new("i018", format!("Invalid format field: {}", field))
+                    })?
+                };
+                match arguments.get(index) {
+                    Some(argument) => result.push_str(&format_value(argument, spec)?),
+                    None => {
+                        return Err(EzError::new(
+                            "i015",
+                            format!("Format index {} out of range", index),
+                        ))
+                    }
+                }
+            }
+            '}' => {
+                return Err(EzError::new(
+                    "i018",
+                    String::from("Single '}' in format string"),
+                ))
+            }
+            _ => result.push(c),
+        }
+    }
+    Ok(result)
+}
+
+fn wrong_arguments(method: &str, arguments: &[Token]) -> EzError {
+    EzError::new(
+        "i017",
+        format!("Invalid arguments for {}: ({})", method, type_names(arguments)),
+    )
+}
+
+// Format spec mini-language of Python: [[fill]align][sign][0][width][,|_][.precision][type]
+struct FormatSpec {
+    fill: char,
+    align: Option<char>,
+    sign: Option<char>,
+    zero: bool,
+    width: usize,
+    grouping: Option<char>,
+    precision: Option<usize>,
+    kind: Option<char>,
+}
+
+// Formats a value for an f-string field or a `format` placeholder
+pub fn format_value(value: &Token, spec: &str) -> Result<String, EzError> {
+    if spec.is_empty() {
+        return display(value);
+    }
+    // Dates take the codes of their `format` method, `{when:%Y-%m-%d}`
+    if let (Token::DateTime(time), true) = (value, spec.contains('%')) {
+        return time.format(spec);
+    }
+    let spec = parse_spec(spec)?;
+    let invalid_kind = || {
+        EzError::new(
+            "i018",
+            format!(
+                "Invalid format type {:?} for {}",
+                spec.kind.unwrap_or('s'),
+                display(value).unwrap_or_default()
+            ),
+        )
+    };
+    let (negative, body, numeric) = match (value, spec.kind) {
+        (Token::Integer(_) | Token::BigInteger(_), None | Some('d')) => {
+            let digits = display(value)?;
+            let (negative, digits) = match digits.strip_prefix('-') {
+                Some(digits) => (true, digits.to_string()),
+                None => (false, digits),
+            };
+            (negative, group_digits(&digits, spec.grouping), true)
+        }
+        (Token::Integer(number), Some(kind @ ('x' | 'X' | 'o' | 'b'))) => {
+            let magnitude = number.unsigned_abs();
+            let digits = match kind {
+                'x' => format!("{:x}", magnitude),
+                'X' => format!("{:X}", magnitude),
+                'o' => format!("{:o}", magnitude),
+                _ => format!("{:b}", magnitude),
+            };
+            (*number < 0, digits, true)
+        }
+        (
+            Token::Integer(_) | Token::BigInteger(_) | Token::Float(_),
+            Some('f' | 'F' | 'e' | 'E' | '%'),
+        )
+        | (Token::Float(_), None) => {
+            let number = match value {
+                Token::Integer(number) => *number as f64,
+                Token::BigInteger(number) => number.to_f64(),
+                Token::Float(number) => *number,
+                _ => 0.0,
+            };
+            if spec.kind.is_none() && spec.precision.is_none() {
+                let text = display(value)?;
+                let negative = text.starts_with('-');
+                (negative, text.trim_start_matches('-').to_string(), true)
+            } else {
+                (
+                    number.is_sign_negative() && !number.is_nan(),
+                    format_float_kind(number.abs(), &spec),
+                    true,
+                )
+            }
+        }
+        (Token::Float(_), _) | (Token::Integer(_) | Token::BigInteger(_), _) => {
+            return Err(invalid_kind())
+        }
+        (_, None | Some('s')) => {
+            if spec.sign.is_some() || spec.grouping.is_some() {
+                return Err(invalid_kind());
+            }
+            let text = display(value)?;
+            let text = match spec.precision {
+                Some(precision) => text.chars().take(precision).collect(),
+                None => text,
+            };
+            (false, text, false)
+        }
+        _ => return Err(invalid_kind()),
+    };
+
+    let sign = match (negative, spec.sign) {
+        (true, _) => "-",
+        (false, Some('+')) => "+",
+        (false, Some(' ')) => " ",
+        _ => "",
+    };
+    let (fill, align) = match (spec.align, spec.zero) {
+        (Some(align), _) => (spec.fill, align),
+        (None, true) => ('0', '='),
+        (None, false) => (' ', if numeric { '>' } else { '<' }),
+    };
+    let padding = spec
+        .width
+        .saturating_sub(sign.chars().count() + body.chars().count());
+    let pad = |count: usize| fill.to_string().repeat(count);
+    Ok(match align {
+        '<' => format!("{}{}{}", sign, body, pad(padding)),
+        '^' => format!(
+            "{}{}{}{}",
+            pad(padding / 2),
+            sign,
+            body,
+            pad(padding - padding / 2)
+        ),
+        '=' => format!("{}{}{}", sign, pad(padding), body),
+        _ => format!("{}{}{}", pad(padding), sign, body),
+    })
+}
+
+fn parse_spec(text: &str) -> Result<FormatSpec, EzError> {
+    let invalid = || EzError::new("i018", format!("Invalid format spec: {}", text));
+    let chars: Vec<char> = text.chars().collect();
+    let mut spec = FormatSpec {
+        fill: ' ',
+        align: None,
+        sign: None,
+        zero: false,
+        width: 0,
+        grouping: None,
+        precision: None,
+        kind: None,
+    };
+    let mut index = 0;
+    if chars.len() >= 2 && "<>^=".contains(chars[1]) {
+        spec.fill = chars[0];
+        spec.align = Some(chars[1]);
+        index = 2;
+    } else if !chars.is_empty() && "<>^=".contains(chars[0]) {
+        spec.align = Some(chars[0]);
+        index = 1;
+    }
+    if index < chars.len() && "+- ".contains(chars[index]) {
+        spec.sign = Some(chars[index]);
+        index += 1;
+    }
+    if index < chars.len() && chars[index] == '0' {
+        spec.zero = true;
+        index += 1;
+    }
+    let (width, next) = parse_number(&chars, index).ok_or_else(invalid)?;
+    spec.width = width.unwrap_or(0);
+    index = next;
+    if index < chars.len() && (chars[index] == ',' || chars[index] == '_') {
+        spec.grouping = Some(chars[index]);
+        index += 1;
+    }
+    if index < chars.len() && chars[index] == '.' {
+        let (precision, next) = parse_number(&chars, index + 1).ok_or_else(invalid)?;
+        spec.precision = Some(precision.ok_or_else(invalid)?);
+        index = next;
+    }
+    if index < chars.len() && "sdfFeE%xXob".contains(chars[index]) {
+        spec.kind = Some(chars[index]);
+        index += 1;
+    }
+    if index != chars.len() {
+        return Err(invalid());
+    }
+    Ok(spec)
+}
+
+// Reads decimal digits starting at index, None if the number is too large
+fn parse_number(chars: &[char], mut index: usize) -> Option<(Option<usize>, usize)> {
+    let mut number: Option<usize> = None;
+    while index < chars.len() && chars[index].is_ascii_digit() {
+        let digit = chars[index] as usize - '0' as usize;
+        number = Some(number.unwrap_or(0) * 10 + digit);
+        if number > Some(MAX_FORMAT_WIDTH) {
+            return None;
+        }
+        index += 1;
+    }
+    Some((number, index))
+}
+
+// Formats the magnitude of a float for the f, e and % types
+fn format_float_kind(number: f64, spec: &FormatSpec) -> String {
+    if number.is_nan() {
+        return String::from("nan");
+    }
+    if number.is_infinite() {
+        return String::from("inf");
+    }
+    let precision = spec.precision.unwrap_or(6);
+    match spec.kind {
+        Some('e' | 'E') => {
+            let scientific = format!("{:.*e}", precision, number);
+            let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+            let exponent: i32 = exponent.parse().unwrap_or(0);
+            let sign = if exponent < 0 { '-' } else { '+' };
+            let text = format!("{}e{}{:02}", mantissa, sign, exponent.abs());
+            if spec.kind == Some('E') {
+                text.to_uppercase()
+            } else {
+                text
+            }
+        }
+        Some('%') => format!(
+            "{}%",
+            group_fixed(&format!("{:.*}", precision, number * 100.0), spec.grouping)
+        ),
+        _ => group_fixed(&format!("{:.*}", precision, number), spec.grouping),
+    }
+}
+
+fn group_fixed(text: &str, grouping: Option<char>) -> String {
+    match text.split_once('.') {
+        Some((whole, fraction)) => format!("{}.{}", group_digits(whole, grouping), fraction),
+        None => group_digits(text, grouping),
+    }
+}
+
+// Inserts the separator between groups of three digits
+fn group_digits(digits: &str, grouping: Option<char>) -> String {
+    let Some(separator) = grouping else {
+        return digits.to_string();
+    };
+    let digits: Vec<char> = digits.chars().collect();
+    let mut groups: Vec<String> = digits
+        .rchunks(3)
+        .map(|group| group.iter().collect())
+        .collect();
+    groups.reverse();
+    groups.join(&separator.to_string())
+}
@@ -0,0 +1,111 @@
+use crate::error::EzError;
+use crate::interpreter::*;
+use crate::time::VirtualClock;
+use crate::tokenizer::*;
+use crate::{read_file_to_string, run_source, STACK_SIZE};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub fn run_tests(root: &Path) -> bool {
+    // A mistyped path must not pass as a run without failures
+    if !root.exists() {
+        eprintln!("Error: Test path not found: {}", root.display());
+        return false;
+    }
+    let mut files = Vec::new();
+    discover_test_files(root, &mut files);
+    files.sort();
+    if files.is_empty() {
+        eprintln!("Error: No *_test.ez files found in {}", root.display());
+        return false;
+    }
+
+    let mut passed = 0;
+    let mut failed = 0;
+    for file in files {
+        println!("{}", file.display());
+        let content = match read_file_to_string(&file.to_string_lossy()) {
+            Ok(content) => content,
+            Err(e) => {
+                println!("  ERROR {}", e);
+                failed += 1;
+                continue;
+            }
+        };
+        // A file that does not tokenize cannot be checked, it counts as a failure
+        let names = match collect_test_names(&content) {
+            Ok(names) => names,
+            Err(error) => {
+                println!("  ERROR {}", error);
+                failed += 1;
+                continue;
+            }
+        };
+        for name in names {
+            // Every test runs in isolation with a fresh interpreter
+            let mut interpreter = Interpreter::new();
+            // Runs on the large stack of the `ezlang` binary
+            interpreter.set_stack_size(STACK_SIZE);
+            interpreter.set_file(&file.to_string_lossy());
+            interpreter.select_test(&name);
+            // Tests never wait, `time.sleep` only moves the clock forward
+            interpreter.set_clock(Box::new(VirtualClock::new()));
+            match run_source(&mut interpreter, &content, false) {
+                Ok(()) => {
+                    println!("  PASS {}", name);
+                    passed += 1;
+                }
+                Err(error) => {
+                    println!("  FAIL {} on line {}: {}", name, error.line, error);
+                    failed += 1;
+                }
+            }
+        }
+    }
+    println!("{} passed, {} failed", passed, failed);
+    failed == 0
+}
+
+fn discover_test_files(path: &Path, files: &mut Vec<PathBuf>) {
+    if path.is_file() {
+        if path.to_string_lossy().ends_with("_test.ez") {
+            files.push(path.to_path_buf());
+        }
+        return;
+    }
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let entry_path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry_path.is_dir() && (name.starts_with('.') || name == "target") {
+            continue;
+        }
+        discover_test_files(&entry_path, files);
+    }
+}
+
+fn collect_test_names(content: &str) -> Result<Vec<String>, EzError> {
+    let mut names = Vec::new();
+    let mut tokenizer = Tokenizer::new(content);
+    let mut line_start = true;
+    // A test block starts a line with `test "name"`
+    loop {
+        let token = tokenizer
+            .next_token()
+            .map_err(|error| error.with_line(tokenizer.line))?;
+        match token {
+            Token::EOF => break,
+            Token::Identifier(ref keyword) if line_start && keyword == "test" => {
+                if let Ok(Token::String(name)) = tokenizer.peek_token() {
+                    names.push(name);
+                }
+            }
+            _ => {}
+        }
+        line_start = token == Token::Newline;
+    }
+    Ok(names)
+}
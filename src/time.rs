@@ -0,0 +1,532 @@
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::error::{argument_count, expected, EzError};
+use crate::interpreter::type_names;
+use crate::tokenizer::Token;
+
+// Functions of the built-in `time` module
+pub const FUNCTIONS: &[&str] = &["now", "time", "monotonic", "sleep", "date", "parse"];
+
+const MICROS_PER_SECOND: i64 = 1_000_000;
+const MICROS_PER_DAY: i64 = 86_400 * MICROS_PER_SECOND;
+// Dates run from the year 1 to 9999 like in Python
+const MIN_DAY: i64 = -719_162;
+const MAX_DAY: i64 = 2_932_896;
+// Start of the virtual clock, 2000-01-01 00:00:00 UTC
+const VIRTUAL_EPOCH: f64 = 946_684_800.0;
+
+const MONTHS: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June", "July", "August", "September",
+    "October", "November", "December",
+];
+const WEEKDAYS: [&str; 7] = [
+    "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday",
+];
+
+// Every clock access of the interpreter goes through this trait, so tests and
+// graders can replace the system clock
+pub trait Clock: fmt::Debug {
+    // Wall time in seconds since 1970-01-01 00:00:00 UTC
+    fn now(&self) -> f64;
+    // Seconds since an arbitrary start, never goes backwards
+    fn monotonic(&self) -> f64;
+    fn sleep(&mut self, seconds: f64);
+}
+
+#[derive(Debug)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        SystemClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> f64 {
+        match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(elapsed) => elapsed.as_secs_f64(),
+            Err(error) => -error.duration().as_secs_f64(),
+        }
+    }
+
+    fn monotonic(&self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+
+    fn sleep(&mut self, seconds: f64) {
+        thread::sleep(Duration::from_secs_f64(seconds));
+    }
+}
+
+// Clock that only moves when the program sleeps, sleeping returns at once
+#[derive(Debug, Default)]
+pub struct VirtualClock {
+    elapsed: f64,
+}
+
+impl VirtualClock {
+    pub fn new() -> Self {
+        VirtualClock { elapsed: 0.0 }
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> f64 {
+        VIRTUAL_EPOCH + self.elapsed
+    }
+
+    fn monotonic(&self) -> f64 {
+        self.elapsed
+    }
+
+    fn sleep(&mut self, seconds: f64) {
+        self.elapsed += seconds;
+    }
+}
+
+// Date and time in UTC with microsecond precision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    // Microseconds since 1970-01-01 00:00:00
+    micros: i64,
+}
+
+// Calendar fields of a date and time
+struct Fields {
+    year: i64,
+    month: i64,
+    day: i64,
+    hour: i64,
+    minute: i64,
+    second: i64,
+    microsecond: i64,
+}
+
+impl DateTime {
+    fn from_micros(micros: i64) -> Result<Self, EzError> {
+        if !(MIN_DAY..=MAX_DAY).contains(&micros.div_euclid(MICROS_PER_DAY)) {
+            return Err(out_of_range());
+        }
+        Ok(DateTime { micros })
+    }
+
+    fn from_seconds(seconds: f64) -> Result<Self, EzError> {
+        let micros = (seconds * MICROS_PER_SECOND as f64).round();
+        if !micros.is_finite() || micros.abs() >= i64::MAX as f64 {
+            return Err(out_of_range());
+        }
+        DateTime::from_micros(micros as i64)
+    }
+
+    fn from_fields(fields: &Fields) -> Result<Self, EzError> {
+        let Fields {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            microsecond,
+        } = *fields;
+        let checks = [
+            (year, 1, 9999, "year"),
+            (month, 1, 12, "month"),
+            (day, 1, days_in_month(year, month.clamp(1, 12)), "day"),
+            (hour, 0, 23, "hour"),
+            (minute, 0, 59, "minute"),
+            (second, 0, 59, "second"),
+            (microsecond, 0, 999_999, "microsecond"),
+        ];
+        for (value, low, high, name) in checks {
+            if !(low..=high).contains(&value) {
+                return Err(EzError::new(
+                    "i018",
+                    format!("{} must be in {}..{}, got {}", name, low, high, value),
+                ));
+            }
+        }
+        let seconds = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second;
+        DateTime::from_micros(seconds * MICROS_PER_SECOND + microsecond)
+    }
+
+    fn fields(&self) -> Fields {
+        let days = self.micros.div_euclid(MICROS_PER_DAY);
+        let rest = self.micros.rem_euclid(MICROS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        let seconds = rest / MICROS_PER_SECOND;
+        Fields {
+            year,
+            month,
+            day,
+            hour: seconds / 3600,
+            minute: seconds / 60 % 60,
+            second: seconds % 60,
+            microsecond: rest % MICROS_PER_SECOND,
+        }
+    }
+
+    // 0 is Monday
+    fn weekday(&self) -> i64 {
+        (self.micros.div_euclid(MICROS_PER_DAY) + 3).rem_euclid(7)
+    }
+
+    pub fn timestamp(&self) -> f64 {
+        self.micros as f64 / MICROS_PER_SECOND as f64
+    }
+
+    pub fn add_seconds(&self, seconds: f64) -> Result<Self, EzError> {
+        let micros = (seconds * MICROS_PER_SECOND as f64).round();
+        if !micros.is_finite() || micros.abs() >= i64::MAX as f64 {
+            return Err(out_of_range());
+        }
+        match self.micros.checked_add(micros as i64) {
+            Some(micros) => DateTime::from_micros(micros),
+            None => Err(out_of_range()),
+        }
+    }
+
+    // Seconds from `earlier` to this time
+    pub fn seconds_since(&self, earlier: &DateTime) -> f64 {
+        (self.micros - earlier.micros) as f64 / MICROS_PER_SECOND as f64
+    }
+
+    // strftime-style codes: %Y %y %m %d %H %I %M %S %f %p %j %a %A %b %B %%
+    pub fn format(&self, format: &str) -> Result<String, EzError> {
+        let fields = self.fields();
+        let mut text = String::new();
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                text.push(c);
+                continue;
+            }
+            let Some(code) = chars.next() else {
+                return Err(EzError::new(
+                    "i018",
+                    String::from("Format ends with a single %"),
+                ));
+            };
+            let part = match code {
+                'Y' => format!("{:04}", fields.year),
+                'y' => format!("{:02}", fields.year % 100),
+                'm' => format!("{:02}", fields.month),
+                'd' => format!("{:02}", fields.day),
+                'H' => format!("{:02}", fields.hour),
+                'I' => format!("{:02}", (fields.hour + 11) % 12 + 1),
+                'M' => format!("{:02}", fields.minute),
+                'S' => format!("{:02}", fields.second),
+                'f' => format!("{:06}", fields.microsecond),
+                'p' => String::from(if fields.hour < 12 { "AM" } else { "PM" }),
+                'j' => format!(
+                    "{:03}",
+                    days_from_civil(fields.year, fields.month, fields.day)
+                        - days_from_civil(fields.year, 1, 1)
+                        + 1
+                ),
+                'a' => WEEKDAYS[self.weekday() as usize][..3].to_string(),
+                'A' => WEEKDAYS[self.weekday() as usize].to_string(),
+                'b' => MONTHS[fields.month as usize - 1][..3].to_string(),
+                'B' => MONTHS[fields.month as usize - 1].to_string(),
+                '%' => String::from("%"),
+                _ => return Err(unknown_code(code)),
+            };
+            text.push_str(&part);
+        }
+        Ok(text)
+    }
+
+    // Reads a date in the given format, the codes are those of `format`
+    // except the weekday and the day of the year
+    pub fn parse(text: &str, format: &str) -> Result<Self, EzError> {
+        let invalid = || {
+            EzError::new(
+                "i018",
+                format!("Date {:?} does not match format {:?}", text, format),
+            )
+        };
+        let mut fields = Fields {
+            year: 1900,
+            month: 1,
+            day: 1,
+            hour: 0,
+            minute: 0,
+            second: 0,
+            microsecond: 0,
+        };
+        let mut afternoon = None;
+        let mut rest = text;
+        let mut codes = format.chars();
+        while let Some(c) = codes.next() {
+            if c != '%' {
+                rest = rest.strip_prefix(c).ok_or_else(invalid)?;
+                continue;
+            }
+            let code = codes.next().ok_or_else(invalid)?;
+            match code {
+                'Y' | 'y' | 'm' | 'd' | 'H' | 'I' | 'M' | 'S' | 'f' => {
+                    let width = match code {
+                        'Y' => 4,
+                        'f' => 6,
+                        _ => 2,
+                    };
+                    let digits = rest
+                        .bytes()
+                        .take(width)
+                        .take_while(u8::is_ascii_digit)
+                        .count();
+                    if digits == 0 {
+                        return Err(invalid());
+                    }
+                    let mut value: i64 = rest[..digits].parse().map_err(|_| invalid())?;
+                    rest = &rest[digits..];
+                    match code {
+                        'Y' => fields.year = value,
+                        'y' => fields.year = if value < 69 { 2000 + value } else { 1900 + value },
+                        'm' => fields.month = value,
+                        'd' => fields.day = value,
+                        'H' | 'I' => fields.hour = value,
+                        'M' => fields.minute = value,
+                        'S' => fields.second = value,
+                        _ => {
+                            // `.5` is half a second
+                            for _ in digits..6 {
+                                value *= 10;
+                            }
+                            fields.microsecond = value;
+                        }
+                    }
+                }
+                'p' => {
+                    let marker = rest.get(..2).ok_or_else(invalid)?;
+                    afternoon = Some(match marker.to_ascii_uppercase().as_str() {
+                        "AM" => false,
+                        "PM" => true,
+                        _ => return Err(invalid()),
+                    });
+                    rest = &rest[2..];
+                }
+                'b' | 'B' => {
+                    let position = MONTHS.iter().position(|month| {
+                        let name = if code == 'b' { &month[..3] } else { month };
+                        rest.get(..name.len())
+                            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(name))
+                    });
+                    let index = position.ok_or_else(invalid)?;
+                    rest = &rest[if code == 'b' { 3 } else { MONTHS[index].len() }..];
+                    fields.month = index as i64 + 1;
+                }
+                '%' => rest = rest.strip_prefix('%').ok_or_else(invalid)?,
+                _ => return Err(unknown_code(code)),
+            }
+        }
+        if !rest.is_empty() {
+            return Err(invalid());
+        }
+        if let Some(afternoon) = afternoon {
+            if !(1..=12).contains(&fields.hour) {
+                return Err(invalid());
+            }
+            fields.hour = fields.hour % 12 + if afternoon { 12 } else { 0 };
+        }
+        DateTime::from_fields(&fields)
+    }
+
+    // `2024-03-01`, `2024-03-01 14:30:00` or `2024-03-01T14:30:00.250`
+    fn parse_iso(text: &str) -> Result<Self, EzError> {
+        let formats = [
+            "%Y-%m-%d",
+            "%Y-%m-%d %H:%M",
+            "%Y-%m-%dT%H:%M",
+            "%Y-%m-%d %H:%M:%S",
+            "%Y-%m-%dT%H:%M:%S",
+            "%Y-%m-%d %H:%M:%S.%f",
+            "%Y-%m-%dT%H:%M:%S.%f",
+        ];
+        for format in formats {
+            if let Ok(time) = DateTime::parse(text, format) {
+                return Ok(time);
+            }
+        }
+        Err(EzError::new(
+            "i018",
+            format!("Invalid ISO date: {:?}", text),
+        ))
+    }
+}
+
+// ISO 8601 with a space, microseconds only when there are some
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fields = self.fields();
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            fields.year, fields.month, fields.day, fields.hour, fields.minute, fields.second
+        )?;
+        if fields.microsecond != 0 {
+            write!(f, ".{:06}", fields.microsecond)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn call(clock: &mut dyn Clock, function: &str, arguments: &[Token]) -> Result<Token, EzError> {
+    match (function, arguments) {
+        ("now", []) => Ok(Token::DateTime(DateTime::from_seconds(clock.now())?)),
+        ("time", []) => Ok(Token::Float(clock.now())),
+        ("monotonic", []) => Ok(Token::Float(clock.monotonic())),
+        ("sleep", [seconds]) => {
+            let seconds = seconds_of(function, seconds)?;
+            if !(0.0..=1e9).contains(&seconds) {
+                return Err(EzError::new(
+                    "i018",
+                    format!("Cannot sleep for {} seconds", seconds),
+                ));
+            }
+            clock.sleep(seconds);
+            Ok(Token::None)
+        }
+        // date(year, month, day) with an optional hour, minute and second
+        ("date", _) if (3..=6).contains(&arguments.len()) => {
+            let mut values = [1, 1, 1, 0, 0, 0];
+            for (value, argument) in values.iter_mut().zip(arguments) {
+                *value = match argument {
+                    Token::Integer(number) => *number,
+                    _ => return Err(expected(function, "integers", argument)),
+                };
+            }
+            let [year, month, day, hour, minute, second] = values;
+            let fields = Fields {
+                year,
+                month,
+                day,
+                hour,
+                minute,
+                second,
+                microsecond: 0,
+            };
+            Ok(Token::DateTime(DateTime::from_fields(&fields)?))
+        }
+        ("parse", [Token::String(text)]) => Ok(Token::DateTime(DateTime::parse_iso(text)?)),
+        ("parse", [Token::String(text), Token::String(format)]) => {
+            Ok(Token::DateTime(DateTime::parse(text, format)?))
+        }
+        ("parse", [value, ..]) if arguments.len() <= 2 => {
+            Err(expected(function, "strings", value))
+        }
+        ("now" | "time" | "monotonic" | "sleep" | "date" | "parse", _) => Err(argument_count(
+            function,
+            match function {
+                "sleep" => "1",
+                "date" => "3 to 6",
+                "parse" => "1 or 2",
+                _ => "0",
+            },
+            arguments,
+        )),
+        _ => Err(EzError::new(
+            "i016",
+            format!("Module time has no attribute: {}", function),
+        )),
+    }
+}
+
+// Fields of a date and time value, `moment.year`
+pub fn attribute(time: &DateTime, name: &str) -> Option<Token> {
+    let fields = time.fields();
+    let value = match name {
+        "year" => fields.year,
+        "month" => fields.month,
+        "day" => fields.day,
+        "hour" => fields.hour,
+        "minute" => fields.minute,
+        "second" => fields.second,
+        "microsecond" => fields.microsecond,
+        "weekday" => time.weekday(),
+        "timestamp" => return Some(Token::Float(time.timestamp())),
+        _ => return None,
+    };
+    Some(Token::Integer(value))
+}
+
+pub fn call_method(time: &DateTime, method: &str, arguments: Vec<Token>) -> Result<Token, EzError> {
+    match (method, arguments.as_slice()) {
+        ("format", [Token::String(format)]) => Ok(Token::String(time.format(format)?)),
+        ("format", _) => Err(EzError::new(
+            "i017",
+            format!("Invalid arguments for format: ({})", type_names(&arguments)),
+        )),
+        _ => Err(EzError::new(
+            "i016",
+            format!("Date has no method: {}", method),
+        )),
+    }
+}
+
+fn seconds_of(function: &str, value: &Token) -> Result<f64, EzError> {
+    match value {
+        Token::Integer(seconds) => Ok(*seconds as f64),
+        Token::BigInteger(seconds) => Ok(seconds.to_f64()),
+        Token::Float(seconds) if !seconds.is_nan() => Ok(*seconds),
+        _ => Err(expected(function, "a number of seconds", value)),
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days since 1970-01-01 of a date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+
+fn unknown_code(code: char) -> EzError {
+    EzError::new("i018", format!("Unknown format code: %{}", code))
+}
+
+fn out_of_range() -> EzError {
+    EzError::new("i013", String::from("Date out of range"))
+}
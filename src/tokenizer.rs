@@ -1,4 +1,9 @@
-use core::panic;
+use crate::bigint::BigInt;
+use crate::error::EzError;
+use crate::objects::{Class, Function, Module, Native, Object};
+use crate::files::File;
+use crate::time::DateTime;
+use std::rc::Rc;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
@@ -6,31 +11,85 @@ pub enum Token {
     None,
     Boolean(bool),
     Integer(i64),
+    BigInteger(BigInt),
     Float(f64),
     String(String),
+    FormatString(Vec<FormatPart>),
+    List(Vec<Token>),
+    // Error object, created by an error type like `ValueError("bad")` or caught by `except`
+    Error(Box<EzError>),
+    Function(Rc<Function>),
+    Native(Native),
+    // Class value, created by a `class` definition
+    Type(Rc<Class>),
+    Object(Object),
+    // Date and time from the `time` module
+    DateTime(DateTime),
+    File(File),
+    // Namespace of an imported module
+    Module(Rc<Module>),
     Identifier(String),
     Comparison(Compare),
     Bind,
+    // `+=` and the other operators followed by `=`
+    CompoundBind(Box<Token>),
     Plus,
     Minus,
     Asterisk,
+    DoubleAsterisk,
     Slash,
+    DoubleSlash,
     Modulo,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
+    ShiftLeft,
+    ShiftRight,
     And,
     Or,
     Not,
     OpenParen,
     CloseParen,
+    OpenBracket,
+    CloseBracket,
+    // Only used by dictionary patterns so far
+    OpenBrace,
+    CloseBrace,
+    Dot,
+    DotDot,
+    DotDotEqual,
     If,
     Else,
     ElseIf,
     For,
     Print,
+    Assert,
+    Try,
+    Except,
+    Finally,
+    Raise,
+    As,
+    Def,
+    Class,
+    Return,
+    Import,
+    From,
+    With,
     Colon,
+    Comma,
+    Newline,
     EOF,
     Unknown,
 }
 
+// Piece of an f-string, fields keep the source of their expression for the parser
+#[derive(Debug, PartialEq, Clone)]
+pub enum FormatPart {
+    Text(String),
+    Field { expression: String, spec: String },
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Compare {
     Equal,
@@ -39,46 +98,89 @@ pub enum Compare {
     LessThan,
     GreaterThanOrEqual,
     LessThanOrEqual,
+    In,
+    NotIn,
 }
 
-#[derive(Debug)]
+// Tokenizes a whole source file. Lines are separated by `Newline` tokens and the
+// indent level is that of the line holding the first token of the statement.
+#[derive(Debug, Clone)]
 pub struct Tokenizer<'a> {
     input: &'a str,
     position: usize,
     pub indent_level: usize,
+    // Line of the most recently returned token, starting at 1
+    pub line: usize,
+    token_start: usize,
+    current_line: usize,
+    line_start: usize,
+    at_line_start: bool,
 }
 
 impl<'a> Tokenizer<'a> {
     pub fn new(input: &'a str) -> Self {
-        let mut indent_level =  0;
-        for c in input.chars() {
-            if c == ' ' {
-                indent_level += 1;
-            } else {
-                break;
-            }
-        }
-
         Tokenizer {
             input,
             position: 0,
-            indent_level: indent_level,
+            indent_level: 0,
+            line: 1,
+            token_start: 0,
+            current_line: 1,
+            line_start: 0,
+            at_line_start: true,
         }
     }
 
-    pub fn next_token(&mut self) -> Token {
+    // Column of the most recently returned token, starting at 1
+    pub fn column(&self) -> usize {
+        let line_start = self.input[..self.token_start]
+            .rfind('\n')
+            .map_or(0, |index| index + 1);
+        self.input[line_start..self.token_start].chars().count() + 1
+    }
+
+    pub fn next_token(&mut self) -> Result<Token, EzError> {
         self.skip_whitespace();
+        self.line = self.current_line;
+        self.token_start = self.position;
         if self.position >= self.input.len() {
-            return Token::EOF;
+            return Ok(Token::EOF);
         }
 
         let current_char = self.current_char();
+        if current_char == '\n' {
+            self.advance();
+            self.current_line += 1;
+            self.line_start = self.position;
+            self.at_line_start = true;
+            return Ok(Token::Newline);
+        }
+        if self.at_line_start {
+            // Only leading spaces count towards the indent
+            self.indent_level = self.input[self.line_start..]
+                .chars()
+                .take_while(|c| *c == ' ')
+                .count();
+            self.at_line_start = false;
+        }
 
-        match current_char {
-            '0'..='9' => self.number(),
+        let token = match current_char {
+            '0'..='9' => return self.number(),
+            '.' if self.next_char().is_ascii_digit() => return self.number(),
+            'r' if matches!(self.next_char(), '"' | '\'') => {
+                self.advance();
+                return self.string(true);
+            }
+            'f' if matches!(self.next_char(), '"' | '\'') => {
+                self.advance();
+                return match self.string(false)? {
+                    Token::String(content) => Ok(Token::FormatString(format_parts(&content)?)),
+                    token => Ok(token),
+                };
+            }
             'a'..='z' | 'A'..='Z' | '_' => self.identifier(),
             '=' => self.equals(),
-            '"' => self.string(),
+            '"' | '\'' => return self.string(false),
             '+' => {
                 self.advance();
                 Token::Plus
@@ -89,11 +191,21 @@ impl<'a> Tokenizer<'a> {
             }
             '*' => {
                 self.advance();
-                Token::Asterisk
+                if self.current_char() == '*' {
+                    self.advance();
+                    Token::DoubleAsterisk
+                } else {
+                    Token::Asterisk
+                }
             }
             '/' => {
                 self.advance();
-                Token::Slash
+                if self.current_char() == '/' {
+                    self.advance();
+                    Token::DoubleSlash
+                } else {
+                    Token::Slash
+                }
             }
             '%' => {
                 self.advance();
@@ -107,24 +219,69 @@ impl<'a> Tokenizer<'a> {
                 self.advance();
                 Token::CloseParen
             }
+            '[' => {
+                self.advance();
+                Token::OpenBracket
+            }
+            ']' => {
+                self.advance();
+                Token::CloseBracket
+            }
+            '{' => {
+                self.advance();
+                Token::OpenBrace
+            }
+            '}' => {
+                self.advance();
+                Token::CloseBrace
+            }
+            '.' => {
+                self.advance();
+                if self.current_char() == '.' {
+                    self.advance();
+                    if self.current_char() == '=' {
+                        self.advance();
+                        Token::DotDotEqual
+                    } else {
+                        Token::DotDot
+                    }
+                } else {
+                    Token::Dot
+                }
+            }
             '&' => {
                 self.advance();
-                Token::And
+                Token::Ampersand
             }
             '|' => {
                 self.advance();
-                Token::Or
+                Token::Pipe
+            }
+            '^' => {
+                self.advance();
+                Token::Caret
+            }
+            '~' => {
+                self.advance();
+                Token::Tilde
             }
             '!' => self.exclamation(),
-                        ':' => {
+            ':' => {
                 self.advance();
                 Token::Colon
             }
+            ',' => {
+                self.advance();
+                Token::Comma
+            }
             '<' => {
                 self.advance();
                 if self.current_char() == '=' {
                     self.advance();
                     Token::Comparison(Compare::LessThanOrEqual)
+                } else if self.current_char() == '<' {
+                    self.advance();
+                    Token::ShiftLeft
                 } else {
                     Token::Comparison(Compare::LessThan)
                 }
@@ -134,6 +291,9 @@ impl<'a> Tokenizer<'a> {
                 if self.current_char() == '=' {
                     self.advance();
                     Token::Comparison(Compare::GreaterThanOrEqual)
+                } else if self.current_char() == '>' {
+                    self.advance();
+                    Token::ShiftRight
                 } else {
                     Token::Comparison(Compare::GreaterThan)
                 }
@@ -142,67 +302,260 @@ impl<'a> Tokenizer<'a> {
                 self.advance();
                 Token::Unknown
             }
+        };
+        let compound = matches!(
+            token,
+            Token::Plus
+                | Token::Minus
+                | Token::Asterisk
+                | Token::DoubleAsterisk
+                | Token::Slash
+                | Token::DoubleSlash
+                | Token::Modulo
+                | Token::Ampersand
+                | Token::Pipe
+                | Token::Caret
+                | Token::ShiftLeft
+                | Token::ShiftRight
+        );
+        if compound && self.current_char() == '=' {
+            self.advance();
+            return Ok(Token::CompoundBind(Box::new(token)));
         }
+        Ok(token)
     }
 
     fn current_char(&self) -> char {
-        self.input.chars().nth(self.position).unwrap()
+        // Past the end of the line reads as a NUL character
+        self.input[self.position..].chars().next().unwrap_or('\0')
     }
 
-    pub fn peek_token(&mut self) -> Token {
-        let position = self.position.clone();
-        self.skip_whitespace();
+    fn next_char(&self) -> char {
+        self.input[self.position..].chars().nth(1).unwrap_or('\0')
+    }
+
+    pub fn peek_token(&mut self) -> Result<Token, EzError> {
+        let saved = self.clone();
         let next_token = self.next_token();
-        self.position = position;
+        *self = saved;
         next_token
     }
 
     fn advance(&mut self) {
-        self.position += 1;
+        // Positions are byte offsets, so step over the whole character
+        if let Some(c) = self.input[self.position..].chars().next() {
+            self.position += c.len_utf8();
+        }
     }
 
     fn skip_whitespace(&mut self) {
-        while self.position < self.input.len() && self.current_char().is_whitespace() {
+        while self.position < self.input.len()
+            && self.current_char().is_whitespace()
+            && self.current_char() != '\n'
+        {
             self.advance();
         }
     }
 
-    fn number(&mut self) -> Token {
+    // Decimal, hexadecimal (0x), octal (0o) and binary (0b) integers, and floats
+    // with an optional exponent. Underscores may separate digits.
+    fn number(&mut self) -> Result<Token, EzError> {
         let start = self.position;
-        let mut point_used = false;
-        while self.position < self.input.len() && self.current_char().is_digit(10) || self.current_char() == '.'{
+        let radix = match (self.current_char(), self.next_char()) {
+            ('0', 'x' | 'X') => 16,
+            ('0', 'o' | 'O') => 8,
+            ('0', 'b' | 'B') => 2,
+            _ => 10,
+        };
+        if radix != 10 {
+            self.advance();
+            self.advance();
+            let digits_start = self.position;
+            self.digits(radix, start)?;
+            let digits = self.input[digits_start..self.position].replace('_', "");
+            self.check_number_end(start)?;
+            return match BigInt::parse_radix(&digits, radix) {
+                Some(number) => Ok(match number.to_i64() {
+                    Some(small) => Token::Integer(small),
+                    None => Token::BigInteger(number),
+                }),
+                None => Err(self.invalid_number(start)),
+            };
+        }
+
+        let mut is_float = false;
+        self.digits(10, start)?;
+        if self.current_char() == '.' && self.next_char() != '.' {
+            is_float = true;
+            self.advance();
+            if self.current_char() == '_' {
+                return Err(self.invalid_number(start));
+            }
+            self.digits(10, start)?;
             if self.current_char() == '.' {
-                if point_used {
-                    panic!("Error t001: Too many points in number literal");
-                }
-                point_used = true;
+                return Err(EzError::new(
+                    "t001",
+                    String::from("Too many points in number literal"),
+                ));
             }
+        }
+        if matches!(self.current_char(), 'e' | 'E') {
+            is_float = true;
             self.advance();
+            if matches!(self.current_char(), '+' | '-') {
+                self.advance();
+            }
+            if !self.current_char().is_ascii_digit() {
+                return Err(self.invalid_number(start));
+            }
+            self.digits(10, start)?;
         }
-        let number_str = &self.input[start..self.position];
-        if point_used {
-            let number = number_str.parse::<f64>().unwrap();
-            return Token::Float(number);
+        self.check_number_end(start)?;
+
+        let number_str = self.input[start..self.position].replace('_', "");
+        if is_float {
+            match number_str.parse::<f64>() {
+                Ok(number) => Ok(Token::Float(number)),
+                Err(_) => Err(self.invalid_number(start)),
+            }
         } else {
-            let number = number_str.parse::<i64>().unwrap();
-            return Token::Integer(number);
+            match number_str.parse::<i64>() {
+                Ok(number) => Ok(Token::Integer(number)),
+                // Too large for i64, switch to an arbitrary-precision integer
+                Err(_) => match BigInt::parse(&number_str) {
+                    Some(number) => Ok(Token::BigInteger(number)),
+                    None => Err(self.invalid_number(start)),
+                },
+            }
         }
     }
 
-    fn string(&mut self) -> Token {
-        self.advance();
-        let start = self.position;
-        while self.position < self.input.len() && self.current_char() != '"' {
+    // Digits of the radix, a single underscore is allowed between two digits
+    fn digits(&mut self, radix: u32, start: usize) -> Result<(), EzError> {
+        let mut previous_digit = false;
+        loop {
+            let c = self.current_char();
+            if c.is_digit(radix) {
+                previous_digit = true;
+            } else if c == '_' && previous_digit && self.next_char().is_digit(radix) {
+                previous_digit = false;
+            } else if c == '_' {
+                return Err(self.invalid_number(start));
+            } else {
+                return Ok(());
+            }
+            self.advance();
+        }
+    }
+
+    // A number may not run straight into letters, as in 12abc or 0x1g
+    fn check_number_end(&mut self, start: usize) -> Result<(), EzError> {
+        if self.current_char().is_alphanumeric() || self.current_char() == '_' {
+            return Err(self.invalid_number(start));
+        }
+        Ok(())
+    }
+
+    fn invalid_number(&mut self, start: usize) -> EzError {
+        // Report the whole malformed literal
+        while self.current_char().is_alphanumeric() || matches!(self.current_char(), '_' | '.') {
+            self.advance();
+        }
+        EzError::new(
+            "t002",
+            format!("Invalid number literal: {}", &self.input[start..self.position]),
+        )
+    }
+
+    // Single, double or triple quoted string. Raw strings keep backslashes as
+    // they are, only triple quoted strings may span several lines.
+    fn string(&mut self, raw: bool) -> Result<Token, EzError> {
+        let quote = self.current_char();
+        let triple = self.input[self.position..].starts_with(&quote.to_string().repeat(3));
+        let quote_length = if triple { 3 } else { 1 };
+        for _ in 0..quote_length {
             self.advance();
         }
-        let string = self.input[start..self.position].to_string();
+        let closing = quote.to_string().repeat(quote_length);
+        let mut string = String::new();
+        loop {
+            if self.input[self.position..].starts_with(&closing) {
+                for _ in 0..quote_length {
+                    self.advance();
+                }
+                return Ok(Token::String(string));
+            }
+            let c = self.current_char();
+            if self.position >= self.input.len() || (c == '\n' && !triple) {
+                return Err(EzError::new(
+                    "t004",
+                    String::from("Unterminated string literal"),
+                ));
+            }
+            self.advance();
+            match c {
+                '\n' => {
+                    self.current_line += 1;
+                    self.line_start = self.position;
+                    string.push(c);
+                }
+                '\\' if !raw => string.push(self.escape()?),
+                _ => string.push(c),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, EzError> {
+        if self.position >= self.input.len() {
+            return Err(EzError::new(
+                "t004",
+                String::from("Unterminated string literal"),
+            ));
+        }
+        let c = self.current_char();
         self.advance();
-        Token::String(string)
+        let escaped = match c {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' | '"' | '\'' => c,
+            'u' if self.current_char() == '{' => {
+                self.advance();
+                let start = self.position;
+                while self.current_char().is_ascii_hexdigit() {
+                    self.advance();
+                }
+                let digits = &self.input[start..self.position];
+                let code_point = u32::from_str_radix(digits, 16).ok().and_then(char::from_u32);
+                match code_point {
+                    Some(escaped) if self.current_char() == '}' => {
+                        self.advance();
+                        escaped
+                    }
+                    _ => {
+                        return Err(EzError::new(
+                            "t003",
+                            format!("Invalid unicode escape: \\u{{{}", digits),
+                        ))
+                    }
+                }
+            }
+            _ => {
+                return Err(EzError::new(
+                    "t003",
+                    format!("Invalid escape sequence: \\{}", c),
+                ))
+            }
+        };
+        Ok(escaped)
     }
 
     fn identifier(&mut self) -> Token {
         let start = self.position;
-        while self.position < self.input.len() && self.current_char().is_alphanumeric() {
+        while self.position < self.input.len()
+            && (self.current_char().is_alphanumeric() || self.current_char() == '_')
+        {
             self.advance();
         }
 
@@ -213,16 +566,47 @@ impl<'a> Tokenizer<'a> {
             "false" => Token::Boolean(false),
             "and" => Token::And,
             "or" => Token::Or,
+            "not" if self.skip_word("in") => Token::Comparison(Compare::NotIn),
             "not" => Token::Not,
+            "is" if self.skip_word("not") => Token::Comparison(Compare::NotEqual),
             "is" => Token::Comparison(Compare::Equal),
+            "in" => Token::Comparison(Compare::In),
             "if" => Token::If,
             "else" => Token::Else,
             "elseif" => Token::ElseIf,
             "print" => Token::Print,
             "for" => Token::For,
+            "assert" => Token::Assert,
+            "try" => Token::Try,
+            "except" => Token::Except,
+            "finally" => Token::Finally,
+            "raise" => Token::Raise,
+            "as" => Token::As,
+            "def" => Token::Def,
+            "class" => Token::Class,
+            "return" => Token::Return,
+            "import" => Token::Import,
+            "from" => Token::From,
+            "with" => Token::With,
             _ => Token::Identifier(token),
         }
     }
+
+    // Consumes the next word on the line if it is the given keyword, for `not in` and `is not`
+    fn skip_word(&mut self, word: &str) -> bool {
+        let rest = &self.input[self.position..];
+        let after_spaces = rest.trim_start_matches([' ', '\t']);
+        if after_spaces.len() == rest.len() || !after_spaces.starts_with(word) {
+            return false;
+        }
+        let after_word = &after_spaces[word.len()..];
+        if after_word.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+            return false;
+        }
+        self.position += rest.len() - after_word.len();
+        true
+    }
+
     fn equals(&mut self) -> Token {
         self.advance();
         if self.current_char() == '=' {
@@ -243,3 +627,71 @@ impl<'a> Tokenizer<'a> {
         }
     }
 }
+
+// Splits f-string content into text and `{expression:spec}` fields, `{{` and `}}`
+// stand for literal braces
+fn format_parts(content: &str) -> Result<Vec<FormatPart>, EzError> {
+    let format_error = |message: &str| EzError::new("t005", format!("{} in f-string", message));
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                if !text.is_empty() {
+                    parts.push(FormatPart::Text(std::mem::take(&mut text)));
+                }
+                let mut expression = String::new();
+                let mut spec: Option<String> = None;
+                let mut depth = 0;
+                let mut quote = None;
+                loop {
+                    let c = chars.next().ok_or_else(|| format_error("Unclosed '{'"))?;
+                    if let Some(spec) = spec.as_mut() {
+                        if c == '}' {
+                            break;
+                        }
+                        spec.push(c);
+                        continue;
+                    }
+                    match (c, quote) {
+                        (_, Some(open)) if c == open => quote = None,
+                        (_, Some(_)) => {}
+                        ('"' | '\'', None) => quote = Some(c),
+                        ('(' | '[' | '{', None) => depth += 1,
+                        (')' | ']', None) => depth -= 1,
+                        ('}', None) if depth == 0 => break,
+                        ('}', None) => depth -= 1,
+                        (':', None) if depth == 0 => {
+                            spec = Some(String::new());
+                            continue;
+                        }
+                        _ => {}
+                    }
+                    expression.push(c);
+                }
+                if expression.trim().is_empty() {
+                    return Err(format_error("Empty expression"));
+                }
+                parts.push(FormatPart::Field {
+                    expression,
+                    spec: spec.unwrap_or_default(),
+                });
+            }
+            '}' => return Err(format_error("Single '}'")),
+            _ => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        parts.push(FormatPart::Text(text));
+    }
+    Ok(parts)
+}
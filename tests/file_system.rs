@@ -0,0 +1,95 @@
+// Programs only see the files of the `FileSystem` given to the interpreter,
+// imports included.
+
+use std::collections::HashMap;
+use std::io;
+
+use ezlang::files::{DeniedFileSystem, FileSystem};
+use ezlang::interpreter::*;
+use ezlang::run_source;
+
+#[derive(Debug, Default)]
+struct MemoryFileSystem {
+    files: HashMap<String, String>,
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn read(&mut self, path: &str) -> io::Result<String> {
+        self.files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+    }
+
+    fn write(&mut self, path: &str, text: &str, append: bool) -> io::Result<()> {
+        let file = self.files.entry(path.to_string()).or_default();
+        if !append {
+            file.clear();
+        }
+        file.push_str(text);
+        Ok(())
+    }
+
+    fn list(&mut self, _path: &str) -> io::Result<Vec<String>> {
+        Ok(self.files.keys().cloned().collect())
+    }
+
+    fn remove(&mut self, path: &str) -> io::Result<()> {
+        self.files
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+    }
+
+    fn exists(&mut self, path: &str) -> bool {
+        self.files.contains_key(path)
+    }
+
+    fn is_dir(&mut self, _path: &str) -> bool {
+        false
+    }
+
+    fn canonicalize(&mut self, path: &str) -> io::Result<String> {
+        Ok(path.to_string())
+    }
+}
+
+fn sandboxed(file: &str, files: &[(&str, &str)]) -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_file_system(Box::new(MemoryFileSystem {
+        files: files
+            .iter()
+            .map(|(path, text)| (path.to_string(), text.to_string()))
+            .collect(),
+    }));
+    interpreter.set_file(file);
+    interpreter
+}
+
+#[test]
+fn imports_go_through_the_file_system() {
+    let mut interpreter = sandboxed("app/main.ez", &[("app/greeting.ez", "name = \"memory\"\n")]);
+    let source = "import greeting\nassert greeting.name == \"memory\", \"module read from memory\"\n";
+    if let Err(error) = run_source(&mut interpreter, source, false) {
+        panic!("{}", error.traceback());
+    }
+
+    // Modules on disk are not visible to the program
+    let mut interpreter = sandboxed("tests/golden/modules/main.ez", &[]);
+    let error = run_source(&mut interpreter, "import strutils\n", false).unwrap_err();
+    assert_eq!(error.message, "No module named strutils");
+}
+
+#[test]
+fn denied_file_system_gives_no_files() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_file_system(Box::new(DeniedFileSystem));
+    interpreter.set_file("app/main.ez");
+    let source = "import files\nassert not files.exists(\"Cargo.toml\"), \"nothing exists\"\n";
+    if let Err(error) = run_source(&mut interpreter, source, false) {
+        panic!("{}", error.traceback());
+    }
+
+    let error = run_source(&mut interpreter, "print(open(\"Cargo.toml\").read())\n", false).unwrap_err();
+    assert_eq!(error.kind, "OSError");
+}
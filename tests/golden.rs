@@ -0,0 +1,123 @@
+// Runs every program in tests/golden through the interpreter and compares the
+// result with the expected `.stdout`, `.stderr` and `.code` files next to it.
+// Missing `.stderr` and `.code` files mean empty output and exit code 0.
+// Modules imported by the programs are found in tests/golden/modules, and a
+// `.args` file holds extra command line arguments for its program.
+//
+// The test runner is also checked for failing when it finds nothing to run.
+//
+// Set EZLANG_BLESS=1 to write the current output as the new expectation.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+fn read_expected(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_default()
+}
+
+fn write_expected(path: &Path, content: &str, keep_empty: bool) {
+    if content.is_empty() && !keep_empty {
+        let _ = fs::remove_file(path);
+    } else {
+        fs::write(path, content).unwrap();
+    }
+}
+
+#[test]
+fn golden_programs() {
+    let dir = golden_dir();
+    let bless = env::var("EZLANG_BLESS").is_ok_and(|value| value == "1");
+
+    let mut programs: Vec<PathBuf> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ez"))
+        .collect();
+    programs.sort();
+    assert!(!programs.is_empty(), "no golden programs in {}", dir.display());
+
+    let mut failures = Vec::new();
+    for program in &programs {
+        let file_name = program.file_name().unwrap().to_str().unwrap();
+        // *_test.ez files are run through the built-in test runner
+        let mut args: Vec<&str> = if file_name.ends_with("_test.ez") {
+            vec!["test", file_name]
+        } else {
+            vec![file_name]
+        };
+        let extra_args = read_expected(&program.with_extension("args"));
+        args.extend(extra_args.split_whitespace());
+        let output = Command::new(env!("CARGO_BIN_EXE_ezlang"))
+            .args(&args)
+            .current_dir(&dir)
+            .env("EZPATH", dir.join("modules"))
+            .env_remove("RUST_BACKTRACE")
+            .output()
+            .unwrap();
+
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        let code = match output.status.code() {
+            Some(0) => String::new(),
+            Some(code) => format!("{}\n", code),
+            None => String::from("signal\n"),
+        };
+
+        let stdout_path = program.with_extension("stdout");
+        let stderr_path = program.with_extension("stderr");
+        let code_path = program.with_extension("code");
+
+        if bless {
+            write_expected(&stdout_path, &stdout, true);
+            write_expected(&stderr_path, &stderr, false);
+            write_expected(&code_path, &code, false);
+            continue;
+        }
+
+        for (kind, actual, path) in [
+            ("stdout", &stdout, &stdout_path),
+            ("stderr", &stderr, &stderr_path),
+            ("exit code", &code, &code_path),
+        ] {
+            let expected = read_expected(path);
+            if &expected != actual {
+                failures.push(format!(
+                    "{}: {} differs\n--- expected\n{}--- actual\n{}",
+                    file_name, kind, expected, actual
+                ));
+            }
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} golden mismatch(es), rerun with EZLANG_BLESS=1 to update:\n\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
+
+// `ezlang test` fails when there is nothing to run, a typo in CI must not pass
+#[test]
+fn test_runner_rejects_missing_tests() {
+    let dir = golden_dir();
+    for (path, message) in [
+        ("does/not/exist", "Error: Test path not found: does/not/exist\n"),
+        ("modules", "Error: No *_test.ez files found in modules\n"),
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_ezlang"))
+            .args(["test", path])
+            .current_dir(&dir)
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1), "ezlang test {}", path);
+        assert_eq!(String::from_utf8_lossy(&output.stderr), message);
+        assert!(output.stdout.is_empty(), "ezlang test {}", path);
+    }
+}
@@ -0,0 +1,157 @@
+// No input may panic the interpreter, errors must come back as `EzError`.
+// Runs the fuzz crate's program generator over a fixed pseudo-random stream so
+// the invariant is also checked by a plain `cargo test`.
+
+#[path = "../fuzz/src/lib.rs"]
+mod generator;
+
+use ezlang::interpreter::*;
+use ezlang::{run_source, STACK_SIZE};
+
+// Test threads get a 2 MiB stack, give the interpreter the stack of the `ezlang` binary
+fn run(source: &str) {
+    let source = source.to_string();
+    std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_stack_size(STACK_SIZE);
+            if let Err(error) = run_source(&mut interpreter, &source, false) {
+                let _ = error.traceback();
+            }
+        })
+        .and_then(|handle| handle.join().map_err(|_| std::io::Error::other("panicked")))
+        .expect("interpreter panicked");
+}
+
+#[test]
+fn known_crashes_return_errors() {
+    for source in [
+        "x =",
+        "x = \"abc\" =",
+        "x = 1 <",
+        "x = 1 >",
+        "x = !",
+        "x = 1.2.3",
+        "x = 99999999999999999999",
+        "x = 9223372036854775807 + 1",
+        "x = 0 - 9223372036854775807 - 2",
+        "x = 4611686018427387904 * 2",
+        "x = 1 / 0",
+        "x = 1 % 0",
+        "x = _",
+        "x = \"héllo\" + 1",
+        "print(é)",
+        "x = \"abc\\",
+        "x = \"\\u{110000}\"",
+        "x = \"\\u{",
+        "x = \"\"\"never closed\nprint(x)",
+        "x = r'",
+        "x = f\"{\"",
+        "x = f\"{(}\"",
+        "x = f\"{}\"",
+        "x = f\"{1:99999999999}\"",
+        "x = f\"{1:.99999999999f}\"",
+        &"(".repeat(10_000),
+        &"not ".repeat(10_000),
+        &"-".repeat(10_000),
+        &format!("def f(n):\n    return {}f(n + 1)\nf(0)", "-".repeat(60)),
+        "x = 0x",
+        "x = 1e",
+        "x = 1__0",
+        "x = -",
+        "x = -\"text\"",
+        "x = 2 ** 99999999999",
+        "x = 3 ** 3 ** 3 ** 3",
+        "x = 1 << 99999999999",
+        "x = -1 >> 99999999999999999999999",
+        "x = 0.0 ** -1",
+        "3 = 4",
+        "a, b = 1",
+        "x = [1]\nx[0][0] = 1",
+        "x = [1]\nx[99999999999999999999] = 1",
+        "x.y = 1",
+        "a, b += 1",
+        "x = 1 if true",
+        &format!("x = {}1", "1 if true else ".repeat(10_000)),
+        "match 1:\n    case [*a, *b]:\n        print(a)",
+        "match [1]:\n    case [[[[_]]]]:\n        print(1)",
+        "case 1:",
+        "raise",
+        "x = \"\u{e9}\u{e9}\" +\n\t\u{e9} = )",
+        "raise 1",
+        "try:\n    raise\nfinally:\n    raise",
+        "try:\nexcept:",
+        "finally:\n    x = 1",
+        "try:\n    1 / 0\nexcept ZeroDivisionError as e:\n    e.message = 1",
+        &(0..1_000)
+            .map(|level| format!("{}try:\n", " ".repeat(level)))
+            .collect::<String>(),
+        &"try:\n".repeat(1_000),
+        "def f():\n    return f()\nf()",
+        "def f(a, a):\n    return a",
+        "class A(A):\n    x = 1",
+        "class A:\n    def m(self):\n        return super().m()\nA().m()",
+        "class A:\n    def __init__(self):\n        self.me = self\n        self.all = [self]\nprint(A())",
+        "class A:\n    return 1",
+        "super().x()",
+        "def f():\n    try:\n        return 1\n    finally:\n        1 / 0\nf()",
+        "def f():\ndef g():\nf(g)",
+        "len = 1\nlen([])",
+        "class A:\n    def __add__(self, other):\n        return self + other\nA() + 1",
+        "class A:\n    def __repr__(self):\n        return self\nprint(A())",
+        "class A:\n    def __iter__(self):\n        return self\na, b = A()",
+        "class A:\n    def __bool__(self):\n        return 1\nprint(not A())",
+        "class A:\n    def __len__(self):\n        return -1\nprint(len(A()))",
+        "class A:\n    def __getitem__(self, i):\n        return self\nx = A()\nx[0][0][0] = 1",
+        "import",
+        "import nowhere_to_be_found",
+        "from import x",
+        "from nowhere_to_be_found import",
+        "import a as",
+        "import math\nprint(math.factorial(99999999))",
+        "import math\nprint(math.round(10 ** 1000, -99999999999))",
+        "import math\nprint(math.round(1.5, 99999999999))",
+        "import math\nprint(math.floor(1e308))\nprint(math.lcm(2 ** 3000, 3 ** 2000, 5))",
+        "import math\nprint(math.log(2 ** 100000))\nprint(math.gcd(2 ** 10000, 6 ** 3000))",
+        "import math\nmath.sqrt = 1",
+        "import random\nrandom.seed(-1)\nprint(random.int(-9223372036854775807 - 1, 9223372036854775807))",
+        "import random\nprint(random.choice(\"\"))",
+        "import random\nprint(random.shuffle([]), random.float(1))",
+        "import time\nprint(time.date(1, 1, 1) - 1)",
+        "import time\nprint(time.now() + 1e300)",
+        "import time\nprint(time.parse(\"\u{e9}\u{e9}\", \"%p\"))",
+        "import time\nprint(time.parse(\"1\", \"%Y%\"))",
+        "import time\nprint(time.date(2024, 1, 1).format(\"%\u{e9}\"))",
+        "import time\nprint(time.parse(\"Ma\u{e9}\", \"%b\"))",
+        "with",
+        "with open(\"Cargo.toml\") as",
+        "with open(1):\n    print(1)",
+        "with open(\"Cargo.toml\") as f:\n    print(f.read(-5))\nprint(f.readline())",
+        "f = open(\"Cargo.toml\")\nf.close()\nf.close()\nf.name = 1",
+        "print(open(\"src\"))",
+        "print(open(\"\u{e9}\", \"w+\"))",
+        "import files\nprint(files.list(\"\u{e9}\"))\nprint(files.join())",
+        "import files\nprint([files.split(\"/\"), files.split(\"\"), files.join(\"\", \"\")])",
+        "class A:\n    def __exit__(self):\n        raise\nwith A():\n    print(1)",
+        "def f():\n    with f():\n        return 1\nf()",
+    ] {
+        run(source);
+    }
+}
+
+#[test]
+fn generated_programs_do_not_panic() {
+    // xorshift64, seeded for reproducibility
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    for _ in 0..2_000 {
+        let mut data = Vec::with_capacity(256);
+        for _ in 0..256 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            data.push(state as u8);
+        }
+        run(&generator::generate_program(&data));
+    }
+}